@@ -1,34 +1,6 @@
-use crate::{enum_stmt::StmtNode, token::Token};
+use std::{cell::Cell, rc::Rc};
 
-pub trait AstNodeVisitor {
-    type Output;
-
-    fn visit_variable(&self, value: &Token) -> Self::Output;
-    fn visit_assign(&self, value: &AssignNode) -> Self::Output;
-    fn visit_literal(&self, value: &LiteralNode) -> Self::Output;
-    fn visit_unary(&self, value: &UnaryNode) -> Self::Output;
-    fn visit_binary(&self, value: &BinaryNode) -> Self::Output;
-    fn visit_grouping(&self, value: &GroupingNode) -> Self::Output;
-    fn visit_logical(&self, value: &LogicalNode) -> Self::Output;
-}
-
-pub trait AstNodeAccept<V: AstNodeVisitor> {
-    fn accept(&self, visitor: V) -> <V as AstNodeVisitor>::Output;
-}
-
-impl<V: AstNodeVisitor> AstNodeAccept<V> for ExprNode {
-    fn accept(&self, visitor: V) -> <V as AstNodeVisitor>::Output {
-        match self {
-            Self::Variable(t) => visitor.visit_variable(t),
-            Self::Assign(t) => visitor.visit_assign(t),
-            Self::Literal(l) => visitor.visit_literal(l),
-            Self::Unary(u) => visitor.visit_unary(u),
-            Self::Binary(b) => visitor.visit_binary(b),
-            Self::Grouping(g) => visitor.visit_grouping(g),
-            Self::Logical(l) => visitor.visit_logical(l),
-        }
-    }
-}
+use crate::{enum_stmt::FunctionNode, token::Token};
 
 pub trait ExprVisitorMut {
     type Output;
@@ -37,9 +9,24 @@ pub trait ExprVisitorMut {
     fn visit_unary(&mut self, value: &UnaryNode) -> Self::Output;
     fn visit_binary(&mut self, value: &BinaryNode) -> Self::Output;
     fn visit_grouping(&mut self, value: &GroupingNode) -> Self::Output;
-    fn visit_variable(&mut self, value: &Token) -> Self::Output;
+    fn visit_variable(&mut self, node: &VariableNode) -> Self::Output;
     fn visit_assign(&mut self, node: &AssignNode) -> Self::Output;
     fn visit_logical(&mut self, node: &LogicalNode) -> Self::Output;
+    fn visit_call(&mut self, node: &CallNode) -> Self::Output;
+    fn visit_get(&mut self, node: &GetNode) -> Self::Output;
+    fn visit_set(&mut self, node: &SetNode) -> Self::Output;
+    fn visit_this(&mut self, node: &VariableNode) -> Self::Output;
+    fn visit_super(&mut self, node: &SuperNode) -> Self::Output;
+    fn visit_lambda(&mut self, node: &Rc<FunctionNode>) -> Self::Output;
+    fn visit_ternary(&mut self, node: &TernaryNode) -> Self::Output;
+    fn visit_sequence(&mut self, exprs: &[ExprNode]) -> Self::Output;
+    fn visit_compound_assign(&mut self, node: &CompoundAssignNode) -> Self::Output;
+    fn visit_update(&mut self, node: &UpdateNode) -> Self::Output;
+    fn visit_list(&mut self, elements: &[ExprNode]) -> Self::Output;
+    fn visit_index(&mut self, node: &IndexNode) -> Self::Output;
+    fn visit_index_set(&mut self, node: &IndexSetNode) -> Self::Output;
+    fn visit_map(&mut self, node: &MapNode) -> Self::Output;
+    fn visit_interpolation(&mut self, parts: &[ExprNode]) -> Self::Output;
 }
 
 pub trait ExprAcceptMut<V: ExprVisitorMut> {
@@ -56,19 +43,53 @@ impl<V: ExprVisitorMut> ExprAcceptMut<V> for ExprNode {
             Self::Binary(b) => visitor.visit_binary(b),
             Self::Grouping(g) => visitor.visit_grouping(g),
             Self::Logical(l) => visitor.visit_logical(l),
+            Self::Call(c) => visitor.visit_call(c),
+            Self::Get(g) => visitor.visit_get(g),
+            Self::Set(s) => visitor.visit_set(s),
+            Self::This(t) => visitor.visit_this(t),
+            Self::Super(s) => visitor.visit_super(s),
+            Self::Lambda(f) => visitor.visit_lambda(f),
+            Self::Ternary(t) => visitor.visit_ternary(t),
+            Self::Sequence(s) => visitor.visit_sequence(s),
+            Self::CompoundAssign(c) => visitor.visit_compound_assign(c),
+            Self::Update(u) => visitor.visit_update(u),
+            Self::List(l) => visitor.visit_list(l),
+            Self::Index(i) => visitor.visit_index(i),
+            Self::IndexSet(i) => visitor.visit_index_set(i),
+            Self::Map(m) => visitor.visit_map(m),
+            Self::Interpolation(i) => visitor.visit_interpolation(i),
         }
     }
 }
 
 #[derive(Debug)]
 pub enum ExprNode {
-    Variable(Token),
+    Variable(VariableNode),
     Assign(AssignNode),
     Literal(LiteralNode),
     Unary(UnaryNode),
     Binary(BinaryNode),
     Grouping(GroupingNode),
     Logical(LogicalNode),
+    Call(CallNode),
+    Get(GetNode),
+    Set(SetNode),
+    This(VariableNode),
+    Super(SuperNode),
+    /// An anonymous function, its name is the fun keyword
+    Lambda(Rc<FunctionNode>),
+    Ternary(TernaryNode),
+    /// Comma seperated expressions, the value is the value of the last one
+    Sequence(Vec<ExprNode>),
+    CompoundAssign(CompoundAssignNode),
+    Update(UpdateNode),
+    /// A list literal, `[a, b, c]`
+    List(Vec<ExprNode>),
+    Index(IndexNode),
+    IndexSet(IndexSetNode),
+    Map(MapNode),
+    /// An interpolated string, the value is every part displayed and joined together
+    Interpolation(Vec<ExprNode>),
 }
 
 impl ExprNode {
@@ -76,6 +97,7 @@ impl ExprNode {
         Self::Assign(AssignNode {
             name,
             value: Box::new(value),
+            depth: Cell::new(None),
         })
     }
 
@@ -101,7 +123,7 @@ impl ExprNode {
     }
 
     pub fn new_variable(token: Token) -> Self {
-        Self::Variable(token)
+        Self::Variable(VariableNode::new(token))
     }
 
     pub fn new_logical(left: ExprNode, operator: Token, right: ExprNode) -> Self {
@@ -111,6 +133,177 @@ impl ExprNode {
             right: Box::new(right),
         })
     }
+
+    pub fn new_call(callee: ExprNode, paren: Token, arguments: Vec<ExprNode>) -> Self {
+        Self::Call(CallNode {
+            callee: Box::new(callee),
+            paren,
+            arguments,
+        })
+    }
+
+    pub fn new_ternary(condition: ExprNode, then_branch: ExprNode, else_branch: ExprNode) -> Self {
+        Self::Ternary(TernaryNode {
+            condition: Box::new(condition),
+            then_branch: Box::new(then_branch),
+            else_branch: Box::new(else_branch),
+        })
+    }
+
+    pub fn new_compound_assign(target: AssignTarget, operator: Token, value: ExprNode) -> Self {
+        Self::CompoundAssign(CompoundAssignNode {
+            target,
+            operator,
+            value: Box::new(value),
+        })
+    }
+
+    pub fn new_update(target: AssignTarget, operator: Token, prefix: bool) -> Self {
+        Self::Update(UpdateNode {
+            target,
+            operator,
+            prefix,
+        })
+    }
+
+    pub fn new_index(object: ExprNode, bracket: Token, index: ExprNode) -> Self {
+        Self::Index(IndexNode {
+            object: Box::new(object),
+            bracket,
+            index: Box::new(index),
+        })
+    }
+
+    pub fn new_index_set(node: IndexNode, value: ExprNode) -> Self {
+        Self::IndexSet(IndexSetNode {
+            object: node.object,
+            bracket: node.bracket,
+            index: node.index,
+            value: Box::new(value),
+        })
+    }
+
+    pub fn new_get(object: ExprNode, name: Token) -> Self {
+        Self::Get(GetNode {
+            object: Box::new(object),
+            name,
+        })
+    }
+
+    pub fn new_set(object: ExprNode, name: Token, value: ExprNode) -> Self {
+        Self::Set(SetNode {
+            object: Box::new(object),
+            name,
+            value: Box::new(value),
+        })
+    }
+}
+
+/// A property access, `object.name`
+#[derive(Debug)]
+pub struct GetNode {
+    pub object: Box<ExprNode>,
+    pub name: Token,
+}
+
+/// A reference to a variable, `this` is stored as a variable named "this"
+#[derive(Debug)]
+pub struct VariableNode {
+    pub name: Token,
+    /// The number of scopes between the reference and the declaration, set by the resolver.
+    /// Globals are not resolved and stay `None`
+    pub depth: Cell<Option<usize>>,
+}
+
+impl VariableNode {
+    pub fn new(name: Token) -> Self {
+        Self {
+            name,
+            depth: Cell::new(None),
+        }
+    }
+}
+
+/// A superclass method access, `super.method`
+#[derive(Debug)]
+pub struct SuperNode {
+    pub keyword: Token,
+    pub method: Token,
+    /// The number of scopes between the expression and the scope that holds "super"
+    pub depth: Cell<Option<usize>>,
+}
+
+/// A map literal, `{key: value}`, the opening brace is kept to report invalid keys
+#[derive(Debug)]
+pub struct MapNode {
+    pub brace: Token,
+    pub entries: Vec<(ExprNode, ExprNode)>,
+}
+
+/// An index access, `object[index]`, the closing bracket is kept for error reporting
+#[derive(Debug)]
+pub struct IndexNode {
+    pub object: Box<ExprNode>,
+    pub bracket: Token,
+    pub index: Box<ExprNode>,
+}
+
+/// An index assignment, `object[index] = value`
+#[derive(Debug)]
+pub struct IndexSetNode {
+    pub object: Box<ExprNode>,
+    pub bracket: Token,
+    pub index: Box<ExprNode>,
+    pub value: Box<ExprNode>,
+}
+
+/// A property assignment, `object.name = value`
+#[derive(Debug)]
+pub struct SetNode {
+    pub object: Box<ExprNode>,
+    pub name: Token,
+    pub value: Box<ExprNode>,
+}
+
+/// A call expression, the closing paren is kept to report errors at the call site
+#[derive(Debug)]
+pub struct CallNode {
+    pub callee: Box<ExprNode>,
+    pub paren: Token,
+    pub arguments: Vec<ExprNode>,
+}
+
+/// The place a compound assignment or an increment writes to
+#[derive(Debug)]
+pub enum AssignTarget {
+    Variable(VariableNode),
+    Property(GetNode),
+    Index(IndexNode),
+}
+
+/// An assignment that combines the target with the value, `target += value`
+#[derive(Debug)]
+pub struct CompoundAssignNode {
+    pub target: AssignTarget,
+    pub operator: Token,
+    pub value: Box<ExprNode>,
+}
+
+/// An increment or decrement, `++target` or `target--`
+#[derive(Debug)]
+pub struct UpdateNode {
+    pub target: AssignTarget,
+    pub operator: Token,
+    /// Prefix updates evaluate to the new value, postfix ones to the old value
+    pub prefix: bool,
+}
+
+/// A conditional expression, `condition ? then_branch : else_branch`
+#[derive(Debug)]
+pub struct TernaryNode {
+    pub condition: Box<ExprNode>,
+    pub then_branch: Box<ExprNode>,
+    pub else_branch: Box<ExprNode>,
 }
 
 #[derive(Debug)]
@@ -124,6 +317,8 @@ pub struct LogicalNode {
 pub struct AssignNode {
     pub name: Token,
     pub value: Box<ExprNode>,
+    /// The number of scopes between the assignment and the declaration, set by the resolver
+    pub depth: Cell<Option<usize>>,
 }
 
 #[derive(Debug)]
@@ -148,76 +343,9 @@ pub struct GroupingNode {
 #[derive(Debug)]
 pub enum LiteralNode {
     String(String),
+    Integer(i64),
     Number(f64),
     True,
     False,
     Nil,
 }
-
-/// Unary Operators
-#[derive(Debug)]
-pub enum UnaryOperators {
-    Minus,
-    Bang,
-}
-
-/// Binary Operators
-#[derive(Debug)]
-pub enum BinaryOperators {
-    EqualEqual,
-    BangEqual,
-    Less,
-    LessEqual,
-    Greater,
-    GreaterEqual,
-    Plus,
-    Minus,
-    Star,
-    Slash,
-}
-
-///  A pretty printer for expressions
-#[derive(Copy, Clone)]
-pub struct PrettyPrinter {}
-
-impl PrettyPrinter {
-    pub fn print(&self, stmts: &[StmtNode]) {
-        println!("{stmts:?}")
-    }
-}
-
-impl AstNodeVisitor for PrettyPrinter {
-    type Output = String;
-
-    fn visit_variable(&self, value: &Token) -> Self::Output {
-        todo!()
-    }
-
-    fn visit_assign(&self, value: &AssignNode) -> Self::Output {
-        todo!()
-    }
-
-    fn visit_logical(&self, value: &LogicalNode) -> Self::Output {
-        todo!();
-    }
-
-    fn visit_binary(&self, binary: &BinaryNode) -> Self::Output {
-        let left_str = binary.left.accept(*self);
-        let right_str = binary.right.accept(*self);
-        format!("( {:?} {left_str} {right_str} )", binary.operator.lexeme)
-    }
-
-    fn visit_unary(&self, unary: &UnaryNode) -> Self::Output {
-        let right_str = unary.right.accept(*self);
-        format!("( {:?} {right_str} )", unary.operator.lexeme)
-    }
-
-    fn visit_literal(&self, literal: &LiteralNode) -> Self::Output {
-        format!("{:?}", literal)
-    }
-
-    fn visit_grouping(&self, grouping: &GroupingNode) -> Self::Output {
-        let expr_str = grouping.inner.accept(*self);
-        format!("( group {expr_str} )")
-    }
-}
@@ -0,0 +1,166 @@
+use std::{cell::RefCell, fmt, rc::Rc};
+
+use crate::{
+    class::LoxInstance,
+    enum_stmt::FunctionNode,
+    environment::Environment,
+    interpreter::{Interpreter, Unwind, Value},
+    token::TokenType,
+    RunTimeError,
+};
+
+/// Anything that can be called with `(args)` in lox
+pub trait LoxCallable: fmt::Debug + fmt::Display {
+    /// The number of arguments the callable expects
+    fn arity(&self) -> usize;
+
+    /// Calls the callable, the interpreter has already checked the arguments match the arity
+    ///
+    /// Takes an `Rc` so callables such as classes can hand out references to themselves
+    fn call(
+        self: Rc<Self>,
+        interpreter: &mut Interpreter,
+        arguments: Vec<Value>,
+    ) -> Result<Value, RunTimeError>;
+}
+
+/// A function declared in lox source
+pub struct LoxFunction {
+    declaration: Rc<FunctionNode>,
+    /// The environment the function was declared in
+    closure: Rc<RefCell<Environment>>,
+    /// The outermost scope of the module the function was declared in
+    globals: Rc<RefCell<Environment>>,
+    /// Initializers always return the instance they were bound to
+    is_initializer: bool,
+}
+
+impl LoxFunction {
+    pub fn new(
+        declaration: Rc<FunctionNode>,
+        closure: Rc<RefCell<Environment>>,
+        globals: Rc<RefCell<Environment>>,
+        is_initializer: bool,
+    ) -> Self {
+        Self {
+            declaration,
+            closure,
+            globals,
+            is_initializer,
+        }
+    }
+
+    /// Creates a copy of the method where `this` refers to the instance
+    pub fn bind(&self, instance: Rc<RefCell<LoxInstance>>) -> LoxFunction {
+        let mut environment = Environment::enclosing(Rc::clone(&self.closure));
+        environment.put("this".to_string(), Value::Instance(instance));
+        LoxFunction::new(
+            Rc::clone(&self.declaration),
+            Rc::new(RefCell::new(environment)),
+            Rc::clone(&self.globals),
+            self.is_initializer,
+        )
+    }
+}
+
+impl LoxCallable for LoxFunction {
+    fn arity(&self) -> usize {
+        self.declaration.params.len()
+    }
+
+    fn call(
+        self: Rc<Self>,
+        interpreter: &mut Interpreter,
+        arguments: Vec<Value>,
+    ) -> Result<Value, RunTimeError> {
+        let mut environment = Environment::enclosing(Rc::clone(&self.closure));
+        for (param, argument) in self.declaration.params.iter().zip(arguments) {
+            environment.put(param.lexeme.clone(), argument);
+        }
+
+        let result =
+            interpreter.execute_in_module(&self.declaration.body, environment, &self.globals);
+        let value = match result {
+            Ok(()) => Value::Nil,
+            Err(Unwind::Return(value)) => value,
+            Err(Unwind::Error(err)) => return Err(err),
+            Err(Unwind::Break | Unwind::Continue) => {
+                unreachable!("Parser rejects loop control outside of loops")
+            }
+        };
+
+        if self.is_initializer {
+            let this = self.closure.borrow().get_local("this");
+            return Ok(this.expect("Initializers are always bound to an instance"));
+        }
+        Ok(value)
+    }
+}
+
+/// The closure can contain the function itself, so only the name and arity are printed
+impl fmt::Debug for LoxFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LoxFunction")
+            .field("name", &self.declaration.name.lexeme)
+            .field("arity", &self.arity())
+            .finish()
+    }
+}
+
+impl fmt::Display for LoxFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.declaration.name.t_type {
+            TokenType::Fun => write!(f, "<anonymous fn>"),
+            _ => write!(f, "<fn {}>", self.declaration.name.lexeme),
+        }
+    }
+}
+
+/// The signature of rust functions that can be called from lox
+pub type NativeFn = dyn Fn(&[Value]) -> Result<Value, RunTimeError>;
+
+/// A function provided by the host, such as `clock`
+pub struct NativeFunction {
+    name: String,
+    arity: usize,
+    function: Box<NativeFn>,
+}
+
+impl NativeFunction {
+    pub fn new(name: String, arity: usize, function: Box<NativeFn>) -> Self {
+        Self {
+            name,
+            arity,
+            function,
+        }
+    }
+}
+
+impl LoxCallable for NativeFunction {
+    fn arity(&self) -> usize {
+        self.arity
+    }
+
+    fn call(
+        self: Rc<Self>,
+        _interpreter: &mut Interpreter,
+        arguments: Vec<Value>,
+    ) -> Result<Value, RunTimeError> {
+        (self.function)(&arguments)
+    }
+}
+
+impl fmt::Debug for NativeFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("NativeFunction")
+            .field("name", &self.name)
+            .field("arity", &self.arity)
+            .finish()
+    }
+}
+
+impl fmt::Display for NativeFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<native fn {}>", self.name)
+    }
+}
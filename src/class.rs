@@ -0,0 +1,123 @@
+use std::{cell::RefCell, collections::HashMap, fmt, rc::Rc};
+
+use crate::{
+    callable::{LoxCallable, LoxFunction},
+    interpreter::{Interpreter, Value},
+    token::Token,
+    RunTimeError,
+};
+
+/// A class declared in lox source, calling it creates a new instance
+#[derive(Debug)]
+pub struct LoxClass {
+    name: String,
+    superclass: Option<Rc<LoxClass>>,
+    methods: HashMap<String, Rc<LoxFunction>>,
+}
+
+impl LoxClass {
+    pub fn new(
+        name: String,
+        superclass: Option<Rc<LoxClass>>,
+        methods: HashMap<String, Rc<LoxFunction>>,
+    ) -> Self {
+        Self {
+            name,
+            superclass,
+            methods,
+        }
+    }
+
+    /// Finds the method on the class, or the closest superclass that defines it
+    pub fn find_method(&self, name: &str) -> Option<Rc<LoxFunction>> {
+        match self.methods.get(name) {
+            Some(method) => Some(Rc::clone(method)),
+            None => self
+                .superclass
+                .as_ref()
+                .and_then(|superclass| superclass.find_method(name)),
+        }
+    }
+}
+
+impl LoxCallable for LoxClass {
+    /// Classes take the same arguments as their initializer
+    fn arity(&self) -> usize {
+        self.find_method("init")
+            .map(|init| init.arity())
+            .unwrap_or(0)
+    }
+
+    fn call(
+        self: Rc<Self>,
+        interpreter: &mut Interpreter,
+        arguments: Vec<Value>,
+    ) -> Result<Value, RunTimeError> {
+        let instance = Rc::new(RefCell::new(LoxInstance::new(Rc::clone(&self))));
+        if let Some(init) = self.find_method("init") {
+            Rc::new(init.bind(Rc::clone(&instance))).call(interpreter, arguments)?;
+        }
+        Ok(Value::Instance(instance))
+    }
+}
+
+impl fmt::Display for LoxClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
+/// An instance of a lox class
+///
+/// Instances are shared between every value that refers to them so fields set through one
+/// reference are seen by all the others
+pub struct LoxInstance {
+    class: Rc<LoxClass>,
+    fields: HashMap<String, Value>,
+}
+
+impl LoxInstance {
+    pub fn new(class: Rc<LoxClass>) -> Self {
+        Self {
+            class,
+            fields: HashMap::new(),
+        }
+    }
+
+    /// Gets a field, or a method bound to the instance. Fields shadow methods
+    pub fn get(instance: &Rc<RefCell<LoxInstance>>, name: &Token) -> Result<Value, RunTimeError> {
+        if let Some(value) = instance.borrow().fields.get(&name.lexeme) {
+            return Ok(value.clone());
+        }
+
+        let method = instance.borrow().class.find_method(&name.lexeme);
+        match method {
+            Some(method) => Ok(Value::Callable(Rc::new(method.bind(Rc::clone(instance))))),
+            None => Err(RunTimeError::UndefinedProperty(name.clone())),
+        }
+    }
+
+    pub fn set(&mut self, name: &Token, value: Value) {
+        self.set_field(&name.lexeme, value);
+    }
+
+    /// Sets a field by name, for instances filled in by the interpreter itself
+    pub fn set_field(&mut self, name: &str, value: Value) {
+        let _ = self.fields.insert(name.to_string(), value);
+    }
+}
+
+/// Fields can refer back to the instance, so only its class is printed
+impl fmt::Debug for LoxInstance {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LoxInstance")
+            .field("class", &self.class.name)
+            .finish_non_exhaustive()
+    }
+}
+
+impl fmt::Display for LoxInstance {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} instance", self.class)
+    }
+}
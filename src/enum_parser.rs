@@ -1,43 +1,105 @@
 use crate::{
-    ast_enum::{ExprNode, LiteralNode},
-    enum_stmt::{BlockNode, IfNode, StmtAcceptorMut, StmtNode, StmtVisitorMut, VarNode},
+    ast_enum::{AssignTarget, ExprNode, LiteralNode, MapNode, SuperNode, VariableNode},
+    enum_stmt::{
+        BlockNode, CatchClause, ClassNode, ForInNode, FunctionNode, IfNode, ImportNode, MatchArm,
+        MatchNode, ReturnNode, StmtNode, ThrowNode, TryNode, VarNode,
+    },
     token::{Token, TokenLiteral, TokenType},
 };
-use std::io::{self, Write};
+use std::{cell::Cell, path::Path, rc::Rc};
 
-use super::SyntaxError;
+use super::{report, SyntaxError};
+
+/// The kind of function body being parsed, used to validate return statements
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FunctionKind {
+    None,
+    Function,
+    Method,
+    Initializer,
+}
+
+impl FunctionKind {
+    /// The name used for the kind in error messages
+    fn name(self) -> &'static str {
+        match self {
+            FunctionKind::Function => "function",
+            _ => "method",
+        }
+    }
+}
+
+/// The kind of class body being parsed, used to validate this and super expressions
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ClassKind {
+    None,
+    Class,
+    Subclass,
+}
 
 pub struct Parser {
     tokens: Vec<Token>,
     current: usize,
+    /// The innermost function body enclosing the current token
+    current_function: FunctionKind,
+    /// The innermost class body enclosing the current token
+    current_class: ClassKind,
+    /// The number of loop bodies enclosing the current token, reset inside of functions
+    loop_depth: usize,
+    errors: Vec<SyntaxError>,
 }
 
 impl Parser {
     pub fn new(tokens: Vec<Token>) -> Self {
-        Self { tokens, current: 0 }
+        Self {
+            tokens,
+            current: 0,
+            current_function: FunctionKind::None,
+            current_class: ClassKind::None,
+            loop_depth: 0,
+            errors: Vec::new(),
+        }
     }
 
-    pub fn parse(&mut self) -> Result<Vec<StmtNode>, SyntaxError> {
+    /// Parses all the statements, errors are reported as they are found and returned once the
+    /// whole source has been parsed
+    pub fn parse(&mut self) -> Result<Vec<StmtNode>, Vec<SyntaxError>> {
         let mut stmts = Vec::new();
         while !self.is_at_end() {
             match self.declaration() {
                 Ok(stmt) => stmts.push(stmt),
-                Err(SyntaxError::UnmatchedToken(token, msg)) => {
-                    self.report(token.line, &token.lexeme, &msg);
-                    self.syncchronize();
-                }
-                Err(SyntaxError::ExpectedToken(_, token, msg)) => {
-                    self.report(token.line, &token.lexeme, &msg);
-                    self.syncchronize();
-                }
-                Err(SyntaxError::InvalidAssignment(token)) => {
-                    self.report(token.line, "", "Invalid assignement target");
+                Err(err) => {
+                    self.error(err);
                     self.syncchronize();
                 }
             }
         }
-        // TODO Do I need to return a copy of the errors here too?
-        Ok(stmts)
+
+        if self.errors.is_empty() {
+            Ok(stmts)
+        } else {
+            Err(std::mem::take(&mut self.errors))
+        }
+    }
+
+    /// Reports and records the error
+    ///
+    /// Errors that leave the parser in a known state are passed here directly so parsing can
+    /// continue without synchronizing
+    fn error(&mut self, err: SyntaxError) {
+        let token = err.token();
+        let location = match err {
+            SyntaxError::InvalidAssignment(_) => "",
+            _ => &token.lexeme,
+        };
+        let location = format!("around '{location}'");
+        report(
+            token.file.as_deref(),
+            token.line,
+            &location,
+            &err.to_string(),
+        );
+        self.errors.push(err);
     }
 
     #[allow(dead_code)]
@@ -51,12 +113,19 @@ impl Parser {
 
             // Keywords that start statements
             if [
+                TokenType::Break,
                 TokenType::Class,
+                TokenType::Continue,
+                TokenType::Do,
                 TokenType::For,
                 TokenType::Fun,
                 TokenType::If,
+                TokenType::Import,
+                TokenType::Match,
                 TokenType::Print,
                 TokenType::Return,
+                TokenType::Throw,
+                TokenType::Try,
                 TokenType::Var,
                 TokenType::While,
             ]
@@ -70,13 +139,150 @@ impl Parser {
     }
 
     fn declaration(&mut self) -> Result<StmtNode, SyntaxError> {
+        if self.matches(&[TokenType::Class]) {
+            return self.class_declaration();
+        }
+
+        // Anonymous functions start expression statements instead
+        if self.check(&TokenType::Fun) && self.check_next(&TokenType::Identifier) {
+            let _ = self.advance();
+            let function = self.function(FunctionKind::Function)?;
+            return Ok(StmtNode::FunDec(function));
+        }
+
         if self.matches(&[TokenType::Var]) {
             return self.var_declaration();
         }
 
+        if self.matches(&[TokenType::Import]) {
+            return self.import_declaration();
+        }
+
         self.statement()
     }
 
+    // import: "import" STRING ( "as" IDENTIFIER )? ";"
+    fn import_declaration(&mut self) -> Result<StmtNode, SyntaxError> {
+        let keyword = self.previous().clone();
+        let path = self.consume(TokenType::String, "Expected module path after 'import'")?;
+        let TokenLiteral::String(file) = &path.literal else {
+            unreachable!("Scanner gives every string token a string literal")
+        };
+        let file = file.clone();
+
+        let name = if self.matches(&[TokenType::As]) {
+            self.consume(TokenType::Identifier, "Expected module name after 'as'")?
+        } else {
+            match module_name(&path, &file) {
+                Some(name) => name,
+                None => {
+                    self.error(SyntaxError::InvalidModuleName(path.clone()));
+                    path
+                }
+            }
+        };
+
+        self.consume(TokenType::Semicolon, "Expected ';' after import")?;
+        Ok(StmtNode::Import(ImportNode {
+            keyword,
+            path: file,
+            name,
+        }))
+    }
+
+    // class IDENTIFIER ( < IDENTIFIER )? { function* }
+    fn class_declaration(&mut self) -> Result<StmtNode, SyntaxError> {
+        let name = self.consume(TokenType::Identifier, "Expected class name")?;
+
+        let mut superclass = None;
+        let mut kind = ClassKind::Class;
+        if self.matches(&[TokenType::Less]) {
+            let superclass_name =
+                self.consume(TokenType::Identifier, "Expected superclass name")?;
+            if superclass_name.lexeme == name.lexeme {
+                self.error(SyntaxError::InheritFromSelf(superclass_name.clone()));
+            }
+            superclass = Some(VariableNode::new(superclass_name));
+            kind = ClassKind::Subclass;
+        }
+
+        self.consume(TokenType::LeftBrace, "Expected '{' before class body")?;
+
+        let enclosing_class = std::mem::replace(&mut self.current_class, kind);
+        let methods = self.class_body();
+        self.current_class = enclosing_class;
+        let methods = methods?;
+
+        self.consume(TokenType::RightBrace, "Expected '}' after class body")?;
+        Ok(StmtNode::ClassDec(ClassNode {
+            name,
+            superclass,
+            methods,
+        }))
+    }
+
+    fn class_body(&mut self) -> Result<Vec<Rc<FunctionNode>>, SyntaxError> {
+        let mut methods = Vec::new();
+        while !self.check(&TokenType::RightBrace) && !self.is_at_end() {
+            methods.push(self.function(FunctionKind::Method)?);
+        }
+        Ok(methods)
+    }
+
+    // IDENTIFIER ( parameters? ) block
+    // The fun keyword must already be consumed for functions, methods do not have one
+    fn function(&mut self, kind: FunctionKind) -> Result<Rc<FunctionNode>, SyntaxError> {
+        let kind_name = kind.name();
+        let name = self.consume(TokenType::Identifier, &format!("Expected {kind_name} name"))?;
+        let kind = if kind == FunctionKind::Method && name.lexeme == "init" {
+            FunctionKind::Initializer
+        } else {
+            kind
+        };
+
+        self.consume(
+            TokenType::LeftParen,
+            &format!("Expected '(' after {kind_name} name"),
+        )?;
+        self.function_body(name, kind)
+    }
+
+    // ( parameters? ) block
+    // The left paren must already be consumed. Anonymous functions use the fun keyword as
+    // their name
+    fn function_body(
+        &mut self,
+        name: Token,
+        kind: FunctionKind,
+    ) -> Result<Rc<FunctionNode>, SyntaxError> {
+        let kind_name = kind.name();
+
+        let mut params = Vec::new();
+        if !self.check(&TokenType::RightParen) {
+            loop {
+                params.push(self.consume(TokenType::Identifier, "Expected parameter name")?);
+                if !self.matches(&[TokenType::Comma]) {
+                    break;
+                }
+            }
+        }
+        self.consume(TokenType::RightParen, "Expected ')' after parameters")?;
+
+        self.consume(
+            TokenType::LeftBrace,
+            &format!("Expected '{{' before {kind_name} body"),
+        )?;
+        let enclosing_function = std::mem::replace(&mut self.current_function, kind);
+        let enclosing_loop_depth = std::mem::take(&mut self.loop_depth);
+        let body = self.block();
+        self.current_function = enclosing_function;
+        self.loop_depth = enclosing_loop_depth;
+        let body = body?;
+
+        let function = FunctionNode { name, params, body };
+        Ok(Rc::new(function))
+    }
+
     fn var_declaration(&mut self) -> Result<StmtNode, SyntaxError> {
         let name = self.consume(TokenType::Identifier, "Expected identifier")?;
         let mut initializer = ExprNode::Literal(LiteralNode::Nil);
@@ -90,7 +296,8 @@ impl Parser {
     }
 
     fn statement(&mut self) -> Result<StmtNode, SyntaxError> {
-        if self.matches(&[TokenType::LeftBrace]) {
+        // A brace followed by a key and a colon opens a map literal rather than a block
+        if !self.starts_map_literal() && self.matches(&[TokenType::LeftBrace]) {
             return self.block_statement();
         }
 
@@ -106,13 +313,119 @@ impl Parser {
             return self.while_statement();
         }
 
+        if self.matches(&[TokenType::Throw]) {
+            return self.throw_statement();
+        }
+
+        if self.matches(&[TokenType::Try]) {
+            return self.try_statement();
+        }
+
+        if self.matches(&[TokenType::Do]) {
+            return self.do_while_statement();
+        }
+
+        if self.matches(&[TokenType::Match]) {
+            return self.match_statement();
+        }
+
         if self.matches(&[TokenType::For]) {
             return self.for_statement();
         }
 
+        if self.matches(&[TokenType::Return]) {
+            return self.return_statement();
+        }
+
+        if self.matches(&[TokenType::Break, TokenType::Continue]) {
+            return self.loop_control_statement();
+        }
+
         self.expression_statement()
     }
 
+    // ( break | continue ) ;
+    fn loop_control_statement(&mut self) -> Result<StmtNode, SyntaxError> {
+        let keyword = self.previous().clone();
+        if self.loop_depth == 0 {
+            self.error(SyntaxError::OutsideLoop(keyword.clone()));
+        }
+
+        self.consume(
+            TokenType::Semicolon,
+            &format!("Expected ';' after '{}'", keyword.lexeme),
+        )?;
+        match keyword.t_type {
+            TokenType::Break => Ok(StmtNode::Break(keyword)),
+            _ => Ok(StmtNode::Continue(keyword)),
+        }
+    }
+
+    // return expr? ;
+    fn return_statement(&mut self) -> Result<StmtNode, SyntaxError> {
+        let keyword = self.previous().clone();
+        if self.current_function == FunctionKind::None {
+            self.error(SyntaxError::ReturnOutsideFunction(keyword.clone()));
+        }
+
+        let mut value = None;
+        if !self.check(&TokenType::Semicolon) {
+            if self.current_function == FunctionKind::Initializer {
+                self.error(SyntaxError::ReturnFromInitializer(keyword.clone()));
+            }
+            value = Some(self.comma_expression()?);
+        }
+
+        self.consume(TokenType::Semicolon, "Expected ';' after return value")?;
+        Ok(StmtNode::Return(ReturnNode { keyword, value }))
+    }
+
+    fn throw_statement(&mut self) -> Result<StmtNode, SyntaxError> {
+        let keyword = self.previous().clone();
+        let value = self.comma_expression()?;
+        self.consume(TokenType::Semicolon, "Expected ';' after thrown value")?;
+        Ok(StmtNode::Throw(ThrowNode { keyword, value }))
+    }
+
+    // try: "try" block ( "catch" "(" IDENTIFIER ")" block )? ( "finally" block )?
+    fn try_statement(&mut self) -> Result<StmtNode, SyntaxError> {
+        self.consume(TokenType::LeftBrace, "Expected '{' after 'try'")?;
+        let body = self.block()?;
+
+        let mut catch = None;
+        if self.matches(&[TokenType::Catch]) {
+            self.consume(TokenType::LeftParen, "Expected '(' after 'catch'")?;
+            let name = self.consume(TokenType::Identifier, "Expected exception variable name")?;
+            self.consume(
+                TokenType::RightParen,
+                "Expected ')' after exception variable",
+            )?;
+            self.consume(TokenType::LeftBrace, "Expected '{' before catch body")?;
+            let body = self.block()?;
+            catch = Some(CatchClause { name, body });
+        }
+
+        let mut finally = None;
+        if self.matches(&[TokenType::Finally]) {
+            self.consume(TokenType::LeftBrace, "Expected '{' after 'finally'")?;
+            finally = Some(self.block()?);
+        }
+
+        if catch.is_none() && finally.is_none() {
+            return Err(SyntaxError::ExpectedToken(
+                TokenType::Catch,
+                self.peek().clone(),
+                "Expected 'catch' or 'finally' after try block".to_string(),
+            ));
+        }
+
+        Ok(StmtNode::Try(TryNode {
+            body,
+            catch,
+            finally,
+        }))
+    }
+
     // Other statments (if ect cant be used in the initializer
     // for ( dec | expr stmt; expr? ; expr? ) statement
     fn for_statement(&mut self) -> Result<StmtNode, SyntaxError> {
@@ -121,6 +434,10 @@ impl Parser {
             "For initializer must start with left paren '('",
         )?;
 
+        if self.check(&TokenType::Identifier) && self.check_next(&TokenType::In) {
+            return self.for_in_statement();
+        }
+
         // Get initializer, Statements will consume the semicolon
         let mut initializer = StmtNode::Expr(ExprNode::Literal(LiteralNode::Nil));
         if self.matches(&[TokenType::Var]) {
@@ -144,31 +461,101 @@ impl Parser {
             "For condition and increment must be seperated by a semicolon ';'",
         )?;
 
-        let increment = if !self.check(&TokenType::RightParen) {
-            let increment_expr = self.expression()?;
-            StmtNode::Expr(increment_expr)
-        } else {
-            StmtNode::Expr(ExprNode::Literal(LiteralNode::Nil))
-        };
+        let mut increment = None;
+        if !self.check(&TokenType::RightParen) {
+            increment = Some(self.expression()?);
+        }
 
         self.consume(
             TokenType::RightParen,
             "For increment must be followed by a right paren ')'",
         )?;
-        let body = self.statement()?;
-
-        // Desugar to while loop
-        let stmts = vec![body, increment];
-        let while_body = StmtNode::Block(BlockNode(stmts));
-        let while_stmt = StmtNode::new_while(condition, while_body);
+        let body = self.loop_body()?;
 
+        // Desugar to while loop, the increment is kept out of the body so continue still runs it
+        let while_stmt = StmtNode::new_for(condition, body, increment);
         let loop_stmts = vec![initializer, while_stmt];
 
-        println!("{loop_stmts:#?}");
-
         Ok(StmtNode::Block(BlockNode(loop_stmts)))
     }
 
+    // match: "match" "(" expression ")" "{" arm* "}"
+    // arm: ( "case" expression ( "," expression )* | "default" ) ":" declaration*
+    fn match_statement(&mut self) -> Result<StmtNode, SyntaxError> {
+        self.consume(TokenType::LeftParen, "Expected '(' after 'match'")?;
+        let subject = self.expression()?;
+        self.consume(TokenType::RightParen, "Expected ')' after match subject")?;
+        self.consume(TokenType::LeftBrace, "Expected '{' before match arms")?;
+
+        let mut arms = Vec::new();
+        let mut default = None;
+        while !self.check(&TokenType::RightBrace) && !self.is_at_end() {
+            if self.matches(&[TokenType::Case]) {
+                let mut patterns = vec![self.expression()?];
+                while self.matches(&[TokenType::Comma]) {
+                    patterns.push(self.expression()?);
+                }
+                self.consume(TokenType::Colon, "Expected ':' after case patterns")?;
+                let body = self.arm_body()?;
+                arms.push(MatchArm { patterns, body });
+            } else if self.matches(&[TokenType::Default]) {
+                let keyword = self.previous().clone();
+                self.consume(TokenType::Colon, "Expected ':' after 'default'")?;
+                let body = self.arm_body()?;
+                if default.is_some() {
+                    self.error(SyntaxError::DuplicateDefault(keyword));
+                } else {
+                    default = Some(body);
+                }
+            } else {
+                return Err(SyntaxError::UnmatchedToken(
+                    self.peek().clone(),
+                    "Expected 'case' or 'default' in match body".to_string(),
+                ));
+            }
+        }
+        self.consume(TokenType::RightBrace, "Expected '}' after match arms")?;
+
+        Ok(StmtNode::Match(MatchNode {
+            subject,
+            arms,
+            default,
+        }))
+    }
+
+    /// Parses the declarations of a match arm up to the next arm or the closing brace
+    fn arm_body(&mut self) -> Result<Vec<StmtNode>, SyntaxError> {
+        let mut stmts = Vec::new();
+        while !self.check(&TokenType::Case)
+            && !self.check(&TokenType::Default)
+            && !self.check(&TokenType::RightBrace)
+            && !self.is_at_end()
+        {
+            stmts.push(self.declaration()?);
+        }
+        Ok(stmts)
+    }
+
+    // for_in: "for" "(" IDENTIFIER "in" expression ")" statement
+    // The opening paren has already been consumed
+    fn for_in_statement(&mut self) -> Result<StmtNode, SyntaxError> {
+        let name = self.advance().clone();
+        let keyword = self.advance().clone();
+        let iterable = self.expression()?;
+        self.consume(
+            TokenType::RightParen,
+            "For in loop must end with right paren ')'",
+        )?;
+
+        let body = self.loop_body()?;
+        Ok(StmtNode::ForIn(ForInNode {
+            name,
+            keyword,
+            iterable,
+            body: Box::new(body),
+        }))
+    }
+
     fn while_statement(&mut self) -> Result<StmtNode, SyntaxError> {
         self.consume(
             TokenType::LeftParen,
@@ -180,10 +567,38 @@ impl Parser {
             "While condition must end with right paren ')'",
         )?;
 
-        let body = self.statement()?;
+        let body = self.loop_body()?;
         Ok(StmtNode::new_while(condition, body))
     }
 
+    // do_while: "do" statement "while" "(" expression ")" ";"
+    fn do_while_statement(&mut self) -> Result<StmtNode, SyntaxError> {
+        let body = self.loop_body()?;
+        self.consume(TokenType::While, "Expected 'while' after do loop body")?;
+        self.consume(
+            TokenType::LeftParen,
+            "While condition must start with left paren '('",
+        )?;
+        let condition = self.expression()?;
+        self.consume(
+            TokenType::RightParen,
+            "While condition must end with right paren ')'",
+        )?;
+        self.consume(
+            TokenType::Semicolon,
+            "Expected ';' after do while condition",
+        )?;
+        Ok(StmtNode::new_do_while(body, condition))
+    }
+
+    /// Parses the body of a loop, break and continue are only valid inside of it
+    fn loop_body(&mut self) -> Result<StmtNode, SyntaxError> {
+        self.loop_depth += 1;
+        let body = self.statement();
+        self.loop_depth -= 1;
+        body
+    }
+
     fn if_statement(&mut self) -> Result<StmtNode, SyntaxError> {
         self.consume(
             TokenType::LeftParen,
@@ -207,6 +622,11 @@ impl Parser {
     }
 
     fn block_statement(&mut self) -> Result<StmtNode, SyntaxError> {
+        Ok(StmtNode::Block(BlockNode(self.block()?)))
+    }
+
+    /// Parses the declarations up to the closing brace, the opening brace must be consumed
+    fn block(&mut self) -> Result<Vec<StmtNode>, SyntaxError> {
         let mut stmts = Vec::new();
 
         while !self.matches(&[TokenType::RightBrace]) && !self.is_at_end() {
@@ -222,13 +642,13 @@ impl Parser {
             ));
         }
 
-        Ok(StmtNode::Block(BlockNode(stmts)))
+        Ok(stmts)
     }
 
     fn print_statement(&mut self) -> Result<StmtNode, SyntaxError> {
         let expr = self.comma_expression()?;
         self.consume(TokenType::Semicolon, "Expected ';'")?;
-        return Ok(StmtNode::Print(expr));
+        Ok(StmtNode::Print(expr))
     }
 
     fn expression_statement(&mut self) -> Result<StmtNode, SyntaxError> {
@@ -240,20 +660,21 @@ impl Parser {
 
     /// Allows multiple expressions to be placed where only a single one is expected
     ///
-    /// The left expression is evalueated and then discaded if a comma exists. The right most
+    /// Every expression is evaluated from left to right and the value of the right most
     /// expression is returned
     ///
     /// Eg comma expr: expr (,expr)*
     fn comma_expression(&mut self) -> Result<ExprNode, SyntaxError> {
-        // TODO Is this the correct functionality? The expression is remove but it will never be
-        // evaluated
-        let mut expr = self.expression()?;
+        let expr = self.expression()?;
+        if !self.check(&TokenType::Comma) {
+            return Ok(expr);
+        }
 
+        let mut exprs = vec![expr];
         while self.matches(&[TokenType::Comma]) {
-            expr = self.expression()?;
+            exprs.push(self.expression()?);
         }
-
-        Ok(expr)
+        Ok(ExprNode::Sequence(exprs))
     }
 
     fn expression(&mut self) -> Result<ExprNode, SyntaxError> {
@@ -263,24 +684,59 @@ impl Parser {
     // TODO Study this, the logic is a little convoluted
     // https://craftinginterpreters.com/statements-and-state.html#assignment-syntax
     fn assignment(&mut self) -> Result<ExprNode, SyntaxError> {
-        let expr = self.logical_or()?;
+        let expr = self.ternary()?;
 
         if self.matches(&[TokenType::Equal]) {
             let equals = self.previous().clone();
             let value = self.assignment()?;
 
             match expr {
-                ExprNode::Variable(name) => {
-                    return Ok(ExprNode::new_assign(name.clone(), value));
+                ExprNode::Variable(variable) => {
+                    return Ok(ExprNode::new_assign(variable.name, value));
+                }
+                ExprNode::Get(get) => {
+                    return Ok(ExprNode::new_set(*get.object, get.name, value));
+                }
+                ExprNode::Index(index) => {
+                    return Ok(ExprNode::new_index_set(index, value));
                 }
                 _ => {
                     return Err(SyntaxError::InvalidAssignment(equals));
                 }
             }
         }
+
+        if self.matches(&[
+            TokenType::PlusEqual,
+            TokenType::MinusEqual,
+            TokenType::StarEqual,
+            TokenType::SlashEqual,
+        ]) {
+            let operator = self.previous().clone();
+            let value = self.assignment()?;
+            let target = assign_target(expr, &operator)?;
+            return Ok(ExprNode::new_compound_assign(target, operator, value));
+        }
         Ok(expr)
     }
 
+    // ternary: logical_or ( "?" expression ":" ternary )?
+    // Right associative so `a ? b : c ? d : e` groups as `a ? b : (c ? d : e)`
+    fn ternary(&mut self) -> Result<ExprNode, SyntaxError> {
+        let condition = self.logical_or()?;
+
+        if self.matches(&[TokenType::Question]) {
+            let then_branch = self.expression()?;
+            self.consume(
+                TokenType::Colon,
+                "Expected ':' after the then branch of a conditional expression",
+            )?;
+            let else_branch = self.ternary()?;
+            return Ok(ExprNode::new_ternary(condition, then_branch, else_branch));
+        }
+        Ok(condition)
+    }
+
     fn logical_or(&mut self) -> Result<ExprNode, SyntaxError> {
         let mut expr = self.logical_and()?;
 
@@ -315,20 +771,32 @@ impl Parser {
     }
 
     fn comparison(&mut self) -> Result<ExprNode, SyntaxError> {
-        let mut expr = self.term()?;
+        let mut expr = self.range()?;
         while self.matches(&[
             TokenType::Greater,
             TokenType::GreaterEqual,
             TokenType::Less,
             TokenType::LessEqual,
+            TokenType::In,
         ]) {
             let operator = self.previous().clone();
-            let right = self.term()?;
+            let right = self.range()?;
             expr = ExprNode::new_binary(expr, operator, right);
         }
         Ok(expr)
     }
 
+    // range: term ( ".." term )?
+    fn range(&mut self) -> Result<ExprNode, SyntaxError> {
+        let start = self.term()?;
+        if self.matches(&[TokenType::DotDot]) {
+            let operator = self.previous().clone();
+            let end = self.term()?;
+            return Ok(ExprNode::new_binary(start, operator, end));
+        }
+        Ok(start)
+    }
+
     fn term(&mut self) -> Result<ExprNode, SyntaxError> {
         let mut expr = self.factor()?;
         while self.matches(&[TokenType::Plus, TokenType::Minus]) {
@@ -341,7 +809,12 @@ impl Parser {
 
     fn factor(&mut self) -> Result<ExprNode, SyntaxError> {
         let mut expr = self.unary()?;
-        while self.matches(&[TokenType::Slash, TokenType::Star]) {
+        while self.matches(&[
+            TokenType::Slash,
+            TokenType::Star,
+            TokenType::Percent,
+            TokenType::TildeSlash,
+        ]) {
             let operator = self.previous().clone();
             let right = self.unary()?;
             expr = ExprNode::new_binary(expr, operator, right);
@@ -352,10 +825,87 @@ impl Parser {
     fn unary(&mut self) -> Result<ExprNode, SyntaxError> {
         if self.matches(&[TokenType::Bang, TokenType::Minus]) {
             let operator = self.previous().clone();
+            // Negating 9223372036854775808 would overflow, so the minus is part of the literal.
+            // Not when `**` binds to the literal first
+            if operator.t_type == TokenType::Minus
+                && self.check_smallest_integer()
+                && !self.check_at(1, &TokenType::StarStar)
+            {
+                let _ = self.advance();
+                return Ok(ExprNode::Literal(LiteralNode::Integer(i64::MIN)));
+            }
             let right = self.unary()?;
             return Ok(ExprNode::new_unary(operator, right));
         }
-        self.primary()
+        if self.matches(&[TokenType::PlusPlus, TokenType::MinusMinus]) {
+            let operator = self.previous().clone();
+            let operand = self.unary()?;
+            let target = assign_target(operand, &operator)?;
+            return Ok(ExprNode::new_update(target, operator, true));
+        }
+        self.exponent()
+    }
+
+    // exponent: postfix ( "**" unary )?
+    // Binds tighter than unary minus on its left, `-2 ** 2` is -4, and is right associative
+    fn exponent(&mut self) -> Result<ExprNode, SyntaxError> {
+        let base = self.postfix()?;
+        if self.matches(&[TokenType::StarStar]) {
+            let operator = self.previous().clone();
+            let power = self.unary()?;
+            return Ok(ExprNode::new_binary(base, operator, power));
+        }
+        Ok(base)
+    }
+
+    // postfix: call ( "++" | "--" )?
+    fn postfix(&mut self) -> Result<ExprNode, SyntaxError> {
+        let expr = self.call()?;
+        if self.matches(&[TokenType::PlusPlus, TokenType::MinusMinus]) {
+            let operator = self.previous().clone();
+            let target = assign_target(expr, &operator)?;
+            return Ok(ExprNode::new_update(target, operator, false));
+        }
+        Ok(expr)
+    }
+
+    // call: primary ( "(" arguments? ")" | "." IDENTIFIER | "[" expression "]" )*
+    fn call(&mut self) -> Result<ExprNode, SyntaxError> {
+        let mut expr = self.primary()?;
+
+        loop {
+            if self.matches(&[TokenType::LeftParen]) {
+                expr = self.finish_call(expr)?;
+            } else if self.matches(&[TokenType::Dot]) {
+                let name =
+                    self.consume(TokenType::Identifier, "Expected property name after '.'")?;
+                expr = ExprNode::new_get(expr, name);
+            } else if self.matches(&[TokenType::LeftBracket]) {
+                let index = self.expression()?;
+                let bracket = self.consume(TokenType::RightBracket, "Expected ']' after index")?;
+                expr = ExprNode::new_index(expr, bracket, index);
+            } else {
+                break;
+            }
+        }
+        Ok(expr)
+    }
+
+    // Arguments are parsed as single expressions so commas seperate them instead of being
+    // treated as a comma expression
+    fn finish_call(&mut self, callee: ExprNode) -> Result<ExprNode, SyntaxError> {
+        let mut arguments = Vec::new();
+        if !self.check(&TokenType::RightParen) {
+            loop {
+                arguments.push(self.expression()?);
+                if !self.matches(&[TokenType::Comma]) {
+                    break;
+                }
+            }
+        }
+
+        let paren = self.consume(TokenType::RightParen, "Expected ')' after arguments")?;
+        Ok(ExprNode::new_call(callee, paren, arguments))
     }
 
     fn primary(&mut self) -> Result<ExprNode, SyntaxError> {
@@ -371,6 +921,14 @@ impl Parser {
         }
         if self.matches(&[TokenType::Number, TokenType::String]) {
             match &self.previous().literal {
+                // 9223372036854775808 without a minus in front of it
+                TokenLiteral::Integer(i64::MIN) => {
+                    self.error(SyntaxError::IntegerTooLarge(self.previous().clone()));
+                    return Ok(ExprNode::Literal(LiteralNode::Integer(i64::MIN)));
+                }
+                TokenLiteral::Integer(i) => {
+                    return Ok(ExprNode::Literal(LiteralNode::Integer(*i)));
+                }
                 TokenLiteral::Number(n) => {
                     return Ok(ExprNode::Literal(LiteralNode::Number(*n)));
                 }
@@ -381,13 +939,83 @@ impl Parser {
             }
         }
 
+        if self.matches(&[TokenType::Interpolation]) {
+            return self.interpolation();
+        }
+
+        if self.check(&TokenType::Fun) && self.check_next(&TokenType::LeftParen) {
+            let keyword = self.advance().clone();
+            let _ = self.advance();
+            let function = self.function_body(keyword, FunctionKind::Function)?;
+            return Ok(ExprNode::Lambda(function));
+        }
+
+        if self.matches(&[TokenType::This]) {
+            let keyword = self.previous().clone();
+            if self.current_class == ClassKind::None {
+                self.error(SyntaxError::ThisOutsideClass(keyword.clone()));
+            }
+            return Ok(ExprNode::This(VariableNode::new(keyword)));
+        }
+
+        if self.matches(&[TokenType::Super]) {
+            let keyword = self.previous().clone();
+            match self.current_class {
+                ClassKind::None => self.error(SyntaxError::SuperOutsideClass(keyword.clone())),
+                ClassKind::Class => {
+                    self.error(SyntaxError::SuperWithoutSuperclass(keyword.clone()))
+                }
+                ClassKind::Subclass => {}
+            }
+
+            self.consume(TokenType::Dot, "Expected '.' after 'super'")?;
+            let method = self.consume(TokenType::Identifier, "Expected superclass method name")?;
+            return Ok(ExprNode::Super(SuperNode {
+                keyword,
+                method,
+                depth: Cell::new(None),
+            }));
+        }
+
         if self.matches(&[TokenType::Identifier]) {
             let token = self.previous().clone();
             return Ok(ExprNode::new_variable(token));
         }
 
+        if self.matches(&[TokenType::LeftBracket]) {
+            let mut elements = Vec::new();
+            if !self.check(&TokenType::RightBracket) {
+                loop {
+                    elements.push(self.expression()?);
+                    if !self.matches(&[TokenType::Comma]) {
+                        break;
+                    }
+                }
+            }
+            self.consume(TokenType::RightBracket, "Expected ']' after list elements")?;
+            return Ok(ExprNode::List(elements));
+        }
+
+        if self.matches(&[TokenType::LeftBrace]) {
+            let brace = self.previous().clone();
+            let mut entries = Vec::new();
+            if !self.check(&TokenType::RightBrace) {
+                loop {
+                    let key = self.expression()?;
+                    self.consume(TokenType::Colon, "Expected ':' after map key")?;
+                    let value = self.expression()?;
+                    entries.push((key, value));
+                    if !self.matches(&[TokenType::Comma]) {
+                        break;
+                    }
+                }
+            }
+            self.consume(TokenType::RightBrace, "Expected '}' after map entries")?;
+            return Ok(ExprNode::Map(MapNode { brace, entries }));
+        }
+
         if self.matches(&[TokenType::LeftParen]) {
-            let expr = self.expression()?;
+            let expr = self.comma_expression()?;
             self.consume(TokenType::RightParen, "Expect \')\' after expression")?;
             return Ok(ExprNode::new_grouping(expr));
         }
@@ -399,6 +1027,35 @@ impl Parser {
         ))
     }
 
+    // interpolation: ( INTERPOLATION expression )+ STRING
+    // The opening interpolation token has already been consumed
+    fn interpolation(&mut self) -> Result<ExprNode, SyntaxError> {
+        let mut parts = Vec::new();
+        loop {
+            if let TokenLiteral::String(s) = &self.previous().literal {
+                if !s.is_empty() {
+                    parts.push(ExprNode::Literal(LiteralNode::String(s.clone())));
+                }
+            }
+            parts.push(self.expression()?);
+
+            if !self.matches(&[TokenType::Interpolation]) {
+                break;
+            }
+        }
+
+        let end = self.consume(
+            TokenType::String,
+            "Expected '}' after interpolated expression",
+        )?;
+        if let TokenLiteral::String(s) = end.literal {
+            if !s.is_empty() {
+                parts.push(ExprNode::Literal(LiteralNode::String(s)));
+            }
+        }
+        Ok(ExprNode::Interpolation(parts))
+    }
+
     fn consume(&mut self, tt: TokenType, err_msg: &str) -> Result<Token, SyntaxError> {
         if self.check(&tt) {
             let token = self.advance();
@@ -412,14 +1069,6 @@ impl Parser {
         }
     }
 
-    fn report(&mut self, line: u64, location: &str, msg: &str) {
-        let mut stderr = io::stderr();
-        stderr
-            .write_all(format!("[line {line}] Error around '{location}': {msg}\n").as_bytes())
-            .unwrap();
-        stderr.flush().unwrap();
-    }
-
     fn matches(&mut self, ops: &[TokenType]) -> bool {
         for op in ops.iter() {
             if self.check(op) {
@@ -444,6 +1093,56 @@ impl Parser {
         &self.peek().t_type == tt
     }
 
+    /// Checks the token after the next one without consuming anything
+    fn check_next(&self, tt: &TokenType) -> bool {
+        self.check_at(1, tt)
+    }
+
+    /// Checks the token `offset` places past the next one without consuming anything
+    /// Whether the next token is 9223372036854775808, which only fits in 64 bits once negated
+    fn check_smallest_integer(&self) -> bool {
+        self.check(&TokenType::Number)
+            && matches!(self.peek().literal, TokenLiteral::Integer(i64::MIN))
+    }
+
+    fn check_at(&self, offset: usize, tt: &TokenType) -> bool {
+        match self.tokens.get(self.current + offset) {
+            Some(token) => &token.t_type == tt,
+            None => false,
+        }
+    }
+
+    /// Whether the brace at the current token opens a map literal rather than a block
+    ///
+    /// It does when a colon follows before the first semicolon or closing brace, ignoring
+    /// anything nested deeper and the colons of ternaries
+    fn starts_map_literal(&self) -> bool {
+        if !self.check(&TokenType::LeftBrace) {
+            return false;
+        }
+
+        let mut depth = 0;
+        let mut ternaries = 0;
+        for token in &self.tokens[self.current + 1..] {
+            match token.t_type {
+                TokenType::LeftParen | TokenType::LeftBracket | TokenType::LeftBrace => depth += 1,
+                TokenType::RightParen | TokenType::RightBracket | TokenType::RightBrace
+                    if depth > 0 =>
+                {
+                    depth -= 1
+                }
+                TokenType::Semicolon | TokenType::RightBrace | TokenType::Eof if depth == 0 => {
+                    return false
+                }
+                TokenType::Question if depth == 0 => ternaries += 1,
+                TokenType::Colon if depth == 0 && ternaries > 0 => ternaries -= 1,
+                TokenType::Colon if depth == 0 => return true,
+                _ => {}
+            }
+        }
+        false
+    }
+
     fn previous(&self) -> &Token {
         self.tokens.get(self.current - 1).unwrap()
     }
@@ -456,3 +1155,35 @@ impl Parser {
         self.tokens.get(self.current).unwrap()
     }
 }
+
+/// Turns the operand of a compound assignment or an increment into the place it writes to
+fn assign_target(expr: ExprNode, operator: &Token) -> Result<AssignTarget, SyntaxError> {
+    match expr {
+        ExprNode::Variable(variable) => Ok(AssignTarget::Variable(variable)),
+        ExprNode::Get(get) => Ok(AssignTarget::Property(get)),
+        ExprNode::Index(index) => Ok(AssignTarget::Index(index)),
+        _ => Err(SyntaxError::InvalidAssignment(operator.clone())),
+    }
+}
+
+/// The name an import without `as` binds the module to, its file name without the extension
+///
+/// Returns `None` when the file name isn't a valid identifier
+fn module_name(path: &Token, file: &str) -> Option<Token> {
+    let stem = Path::new(file).file_stem()?.to_str()?;
+    let mut chars = stem.chars();
+    let starts_identifier = chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_');
+    if !starts_identifier || !chars.all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        return None;
+    }
+
+    Some(Token::new(
+        TokenType::Identifier,
+        stem.to_string(),
+        TokenLiteral::None,
+        path.line,
+        path.file.clone(),
+    ))
+}
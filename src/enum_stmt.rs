@@ -1,4 +1,9 @@
-use crate::{ast_enum::ExprNode, token::Token, RunTimeError};
+use std::rc::Rc;
+
+use crate::{
+    ast_enum::{ExprNode, VariableNode},
+    token::Token,
+};
 
 #[derive(Debug)]
 pub enum StmtNode {
@@ -8,31 +13,67 @@ pub enum StmtNode {
     Block(BlockNode),
     IfStmt(IfNode),
     WhileStmt(WhileNode),
+    /// A loop that checks its condition after running the body
+    DoWhile(WhileNode),
+    ForIn(ForInNode),
+    FunDec(Rc<FunctionNode>),
+    Return(ReturnNode),
+    ClassDec(ClassNode),
+    Break(Token),
+    Continue(Token),
+    Match(MatchNode),
+    Throw(ThrowNode),
+    Try(TryNode),
+    Import(ImportNode),
 }
 
 pub trait StmtAcceptorMut<V: StmtVisitorMut> {
-    fn accept(&self, visitor: &mut V) -> Result<(), RunTimeError>;
+    fn accept(&self, visitor: &mut V) -> <V as StmtVisitorMut>::Output;
 }
 
 pub trait StmtVisitorMut {
-    fn visit_print(&mut self, expr_node: &ExprNode) -> Result<(), RunTimeError>;
+    type Output;
+
+    fn visit_print(&mut self, expr_node: &ExprNode) -> Self::Output;
+
+    fn visit_expr(&mut self, expr_node: &ExprNode) -> Self::Output;
+
+    fn visit_var_dec(&mut self, var_node: &VarNode) -> Self::Output;
+
+    fn visit_block(&mut self, block_node: &BlockNode) -> Self::Output;
+
+    fn visit_if(&mut self, if_stmt: &IfNode) -> Self::Output;
+
+    fn visit_while(&mut self, while_stmt: &WhileNode) -> Self::Output;
 
-    fn visit_expr(&mut self, expr_node: &ExprNode) -> Result<(), RunTimeError>;
+    fn visit_do_while(&mut self, while_stmt: &WhileNode) -> Self::Output;
 
-    fn visit_var_dec(&mut self, var_node: &VarNode) -> Result<(), RunTimeError>;
+    fn visit_for_in(&mut self, for_in: &ForInNode) -> Self::Output;
 
-    fn visit_block(&mut self, block_node: &BlockNode) -> Result<(), RunTimeError>;
+    fn visit_fun_dec(&mut self, fun_node: &Rc<FunctionNode>) -> Self::Output;
 
-    fn visit_if(&mut self, if_stmt: &IfNode) -> Result<(), RunTimeError>;
+    fn visit_return(&mut self, return_node: &ReturnNode) -> Self::Output;
 
-    fn visit_while(&mut self, while_stmt: &WhileNode) -> Result<(), RunTimeError>;
+    fn visit_class_dec(&mut self, class_node: &ClassNode) -> Self::Output;
+
+    fn visit_break(&mut self, keyword: &Token) -> Self::Output;
+
+    fn visit_continue(&mut self, keyword: &Token) -> Self::Output;
+
+    fn visit_match(&mut self, match_node: &MatchNode) -> Self::Output;
+
+    fn visit_throw(&mut self, throw_node: &ThrowNode) -> Self::Output;
+
+    fn visit_try(&mut self, try_node: &TryNode) -> Self::Output;
+
+    fn visit_import(&mut self, import_node: &ImportNode) -> Self::Output;
 }
 
 impl<V> StmtAcceptorMut<V> for StmtNode
 where
     V: StmtVisitorMut,
 {
-    fn accept(&self, visitor: &mut V) -> Result<(), RunTimeError> {
+    fn accept(&self, visitor: &mut V) -> <V as StmtVisitorMut>::Output {
         match self {
             Self::Print(expr) => visitor.visit_print(expr),
             Self::Expr(expr) => visitor.visit_expr(expr),
@@ -40,14 +81,112 @@ where
             Self::Block(node) => visitor.visit_block(node),
             Self::IfStmt(node) => visitor.visit_if(node),
             Self::WhileStmt(node) => visitor.visit_while(node),
+            Self::DoWhile(node) => visitor.visit_do_while(node),
+            Self::ForIn(node) => visitor.visit_for_in(node),
+            Self::FunDec(node) => visitor.visit_fun_dec(node),
+            Self::Return(node) => visitor.visit_return(node),
+            Self::ClassDec(node) => visitor.visit_class_dec(node),
+            Self::Break(keyword) => visitor.visit_break(keyword),
+            Self::Continue(keyword) => visitor.visit_continue(keyword),
+            Self::Match(node) => visitor.visit_match(node),
+            Self::Throw(node) => visitor.visit_throw(node),
+            Self::Try(node) => visitor.visit_try(node),
+            Self::Import(node) => visitor.visit_import(node),
         }
     }
 }
 
+/// An import statement, binds the namespace of the module to the name
+#[derive(Debug)]
+pub struct ImportNode {
+    pub keyword: Token,
+    /// The path of the module, relative to the file containing the import
+    pub path: String,
+    /// The name after `as`, or one made from the file name when there is none
+    pub name: Token,
+}
+
+/// A throw statement, the keyword is kept to report uncaught exceptions
+#[derive(Debug)]
+pub struct ThrowNode {
+    pub keyword: Token,
+    pub value: ExprNode,
+}
+
+/// A try statement, it has a catch clause, a finally block or both
+#[derive(Debug)]
+pub struct TryNode {
+    pub body: Vec<StmtNode>,
+    pub catch: Option<CatchClause>,
+    /// Runs however the body and the catch clause finish
+    pub finally: Option<Vec<StmtNode>>,
+}
+
+/// `catch (name) { body }`, the name is bound to the caught value
+#[derive(Debug)]
+pub struct CatchClause {
+    pub name: Token,
+    pub body: Vec<StmtNode>,
+}
+
+/// A match statement, only the first arm with a pattern equal to the subject runs
+#[derive(Debug)]
+pub struct MatchNode {
+    pub subject: ExprNode,
+    pub arms: Vec<MatchArm>,
+    /// Runs when no arm matches
+    pub default: Option<Vec<StmtNode>>,
+}
+
+/// A `case` of a match statement, its body is a scope of its own
+#[derive(Debug)]
+pub struct MatchArm {
+    pub patterns: Vec<ExprNode>,
+    pub body: Vec<StmtNode>,
+}
+
+/// A class declaration
+#[derive(Debug)]
+pub struct ClassNode {
+    pub name: Token,
+    pub superclass: Option<VariableNode>,
+    pub methods: Vec<Rc<FunctionNode>>,
+}
+
+/// A function declaration
+///
+/// Shared behind an `Rc` so function values can outlive the statements they were parsed from
+#[derive(Debug)]
+pub struct FunctionNode {
+    pub name: Token,
+    pub params: Vec<Token>,
+    pub body: Vec<StmtNode>,
+}
+
+/// A return statement, the keyword is kept for error reporting
+#[derive(Debug)]
+pub struct ReturnNode {
+    pub keyword: Token,
+    pub value: Option<ExprNode>,
+}
+
 #[derive(Debug)]
 pub struct WhileNode {
     pub condition: ExprNode,
     pub body: Box<StmtNode>,
+    /// The increment of a desugared for loop, it runs after the body even when it continues
+    pub increment: Option<ExprNode>,
+}
+
+/// A loop over the items of an iterable, `for (name in iterable) body`
+///
+/// The in keyword is kept to report values that can't be iterated
+#[derive(Debug)]
+pub struct ForInNode {
+    pub name: Token,
+    pub keyword: Token,
+    pub iterable: ExprNode,
+    pub body: Box<StmtNode>,
 }
 
 #[derive(Debug)]
@@ -84,10 +223,23 @@ impl VarNode {
 
 impl StmtNode {
     pub fn new_while(condition: ExprNode, body: StmtNode) -> Self {
+        Self::new_for(condition, body, None)
+    }
+
+    pub fn new_for(condition: ExprNode, body: StmtNode, increment: Option<ExprNode>) -> Self {
         let node = WhileNode {
             condition,
             body: Box::new(body),
+            increment,
         };
         Self::WhileStmt(node)
     }
+
+    pub fn new_do_while(body: StmtNode, condition: ExprNode) -> Self {
+        Self::DoWhile(WhileNode {
+            condition,
+            body: Box::new(body),
+            increment: None,
+        })
+    }
 }
@@ -1,16 +1,14 @@
 use crate::{interpreter::Value, token::Token, RunTimeError};
-use std::collections::HashMap;
-use std::default;
-
-// TODO Figure out how to do enclosing without a clone
-// Probably the easiest way to deal with this to have using an Option::take
-// Also look to desing of too many linked lists (this is similar to what Environment needs
-// Probably better to encapsulate the enclosing/popping  of enviroments (could use Drop to make
-// popping automatic
-#[derive(Debug, Clone, Default)]
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
+
+/// A single scope of variables
+///
+/// Scopes are shared so that closures and inner scopes can read and mutate the variables of
+/// the scopes enclosing them
+#[derive(Debug, Default)]
 pub struct Environment {
     values: HashMap<String, Value>,
-    enclosing: Option<Box<Environment>>,
+    enclosing: Option<Rc<RefCell<Environment>>>,
 }
 
 impl Environment {
@@ -21,42 +19,74 @@ impl Environment {
         }
     }
 
-    pub fn enclosing(enclosing: Environment) -> Self {
+    pub fn enclosing(enclosing: Rc<RefCell<Environment>>) -> Self {
         Self {
-            enclosing: Some(Box::new(enclosing)),
+            enclosing: Some(enclosing),
             values: HashMap::new(),
         }
     }
 
-    pub fn parent(&self) -> Option<&Box<Environment>> {
-        self.enclosing.as_ref()
-    }
-
     pub fn put(&mut self, name: String, value: Value) {
         let _ = self.values.insert(name, value);
     }
 
+    /// Gets a variable declared directly in this scope, ignoring the enclosing scopes
+    pub fn get_local(&self, name: &str) -> Option<Value> {
+        self.values.get(name).cloned()
+    }
+
     pub fn get(&self, token: &Token) -> Result<Value, RunTimeError> {
-        match self.values.get(&token.lexeme).cloned() {
-            Some(value) => Ok(value),
-            None => match &self.enclosing {
-                Some(env) => env.get(token),
-                None => Err(RunTimeError::UndefinedVariable(token.clone())),
-            },
+        self.lookup(&token.lexeme)
+            .ok_or_else(|| RunTimeError::UndefinedVariable(token.clone()))
+    }
+
+    /// Gets the variable from the closest scope that declares it
+    pub fn lookup(&self, name: &str) -> Option<Value> {
+        match self.values.get(name) {
+            Some(value) => Some(value.clone()),
+            None => self
+                .enclosing
+                .as_ref()
+                .and_then(|env| env.borrow().lookup(name)),
+        }
+    }
+
+    /// Gets a variable declared in the scope `distance` scopes above this one
+    pub fn get_at(&self, distance: usize, name: &str) -> Option<Value> {
+        match distance {
+            0 => self.get_local(name),
+            _ => self.ancestor(distance).borrow().get_local(name),
+        }
+    }
+
+    /// Assigns a variable declared in the scope `distance` scopes above this one
+    pub fn assign_at(
+        &mut self,
+        distance: usize,
+        token: &Token,
+        value: Value,
+    ) -> Result<(), RunTimeError> {
+        if distance > 0 {
+            return self
+                .ancestor(distance)
+                .borrow_mut()
+                .assign_at(0, token, value);
+        }
+
+        match self.values.get_mut(&token.lexeme) {
+            Some(v) => {
+                *v = value;
+                Ok(())
+            }
+            None => Err(RunTimeError::UndefinedVariable(token.clone())),
         }
     }
 
-    pub fn assign(&mut self, token: &Token, value: Value) -> Result<(), RunTimeError> {
-        match self
-            .values
-            .get_mut(&token.lexeme)
-            .map(|v| *v = value.clone())
-        {
-            Some(v) => Ok(v),
-            None => match &mut self.enclosing {
-                Some(env) => env.assign(token, value),
-                None => Err(RunTimeError::UndefinedVariable(token.clone())),
-            },
+    fn ancestor(&self, distance: usize) -> Rc<RefCell<Environment>> {
+        let mut environment = self.enclosing.clone();
+        for _ in 1..distance {
+            environment = environment.and_then(|env| env.borrow().enclosing.clone());
         }
+        environment.expect("Resolver only creates depths of scopes that exist")
     }
 }
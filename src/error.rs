@@ -1,4 +1,7 @@
-use crate::token::{Token, TokenType};
+use crate::{
+    interpreter::Value,
+    token::{Token, TokenType},
+};
 
 #[derive(Debug, Clone)]
 pub enum SyntaxError {
@@ -7,10 +10,215 @@ pub enum SyntaxError {
     /// Contains the expected token type, the token found, the a message
     ExpectedToken(TokenType, Token, String),
     InvalidAssignment(Token),
+    /// Contains the return keyword found outside of a function body
+    ReturnOutsideFunction(Token),
+    /// Contains the return keyword of an initializer that returns a value
+    ReturnFromInitializer(Token),
+    /// Contains the this keyword found outside of a class body
+    ThisOutsideClass(Token),
+    /// Contains the super keyword found outside of a class body
+    SuperOutsideClass(Token),
+    /// Contains the super keyword found in a class without a superclass
+    SuperWithoutSuperclass(Token),
+    /// Contains the name of a class that inherits from itself
+    InheritFromSelf(Token),
+    /// Contains the name of a local variable read in its own initializer
+    ReadInOwnInitializer(Token),
+    /// Contains the name of a local variable declared twice in the same scope
+    AlreadyDeclared(Token),
+    /// Contains the break or continue keyword found outside of a loop body
+    OutsideLoop(Token),
+    /// Contains the integer literal 9223372036854775808 found without a minus in front of it
+    IntegerTooLarge(Token),
+    /// Contains the second default keyword of a match statement
+    DuplicateDefault(Token),
+    /// Contains the path of an import whose file name can't be used as a variable name
+    InvalidModuleName(Token),
+}
+
+impl SyntaxError {
+    /// The token the error was found at
+    pub fn token(&self) -> &Token {
+        match self {
+            Self::UnmatchedToken(token, _)
+            | Self::ExpectedToken(_, token, _)
+            | Self::InvalidAssignment(token)
+            | Self::ReturnOutsideFunction(token)
+            | Self::ReturnFromInitializer(token)
+            | Self::ThisOutsideClass(token)
+            | Self::SuperOutsideClass(token)
+            | Self::SuperWithoutSuperclass(token)
+            | Self::InheritFromSelf(token)
+            | Self::ReadInOwnInitializer(token)
+            | Self::AlreadyDeclared(token)
+            | Self::OutsideLoop(token)
+            | Self::IntegerTooLarge(token)
+            | Self::DuplicateDefault(token)
+            | Self::InvalidModuleName(token) => token,
+        }
+    }
+}
+
+impl std::fmt::Display for SyntaxError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnmatchedToken(_, msg) | Self::ExpectedToken(_, _, msg) => write!(f, "{msg}"),
+            Self::InvalidAssignment(_) => write!(f, "Invalid assignement target"),
+            Self::ReturnOutsideFunction(_) => write!(f, "Can't return from top-level code"),
+            Self::ReturnFromInitializer(_) => {
+                write!(f, "Can't return a value from an initializer")
+            }
+            Self::ThisOutsideClass(_) => write!(f, "Can't use 'this' outside of a class"),
+            Self::SuperOutsideClass(_) => write!(f, "Can't use 'super' outside of a class"),
+            Self::SuperWithoutSuperclass(_) => {
+                write!(f, "Can't use 'super' in a class with no superclass")
+            }
+            Self::InheritFromSelf(_) => write!(f, "A class can't inherit from itself"),
+            Self::ReadInOwnInitializer(_) => {
+                write!(f, "Can't read local variable in its own initializer")
+            }
+            Self::AlreadyDeclared(_) => {
+                write!(f, "Already a variable with this name in this scope")
+            }
+            Self::OutsideLoop(token) => write!(f, "Can't use '{}' outside of a loop", token.lexeme),
+            Self::IntegerTooLarge(_) => write!(
+                f,
+                "Integer literal is too large, integers go from -9223372036854775808 to 9223372036854775807"
+            ),
+            Self::DuplicateDefault(_) => {
+                write!(f, "A match statement can only have one default arm")
+            }
+            Self::InvalidModuleName(_) => {
+                write!(
+                    f,
+                    "File name isn't a valid variable name, name the module with 'as'"
+                )
+            }
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
 pub enum RunTimeError {
-    InvalidBangValue(Token, String),
+    /// Contains the unary or update operator applied to an operand that isn't a number
+    InvalidOperand(Token, String),
+    /// Contains the operator applied to operands of the wrong type
+    InvalidOperands(Token, String),
+    /// Contains the modulo or integer division operator whose right operand was zero
+    DivisionByZero(Token),
+    /// Contains the operator whose integer result doesn't fit in 64 bits
+    IntegerOverflow(Token),
+    /// Contains the closing bracket of an index that isn't a whole number within the list
+    InvalidIndex(Token, String),
+    /// Contains the closing bracket of an index into a value that can't be indexed
+    NotIndexable(Token, String),
+    /// Contains the closing bracket of a read of a key that isn't in the map, and the key
+    UndefinedKey(Token, String),
+    /// Contains the closing bracket of an index or the opening brace of a map literal whose key
+    /// can't be a map key
+    InvalidKey(Token, String),
+    /// Contains the in keyword of a for loop over a value that can't be iterated
+    NotIterable(Token, String),
     UndefinedVariable(Token),
+    /// Contains the name of a builtin assigned to in a module that doesn't shadow it
+    AssignToBuiltin(Token),
+    /// Contains the closing paren of the call, the expected and the received argument counts
+    ArityMismatch(Token, usize, usize),
+    /// Contains the closing paren of a call whose callee is not a function
+    NotCallable(Token, String),
+    /// Contains the name of a property that isn't defined on the instance
+    UndefinedProperty(Token),
+    /// Contains the name of a property accessed on a value that isn't an instance
+    NotAnInstance(Token, String),
+    /// Contains the name of the superclass that evaluated to something other than a class
+    SuperclassNotClass(Token),
+    /// Raised by a native function, the call site is filled in by the interpreter
+    NativeError(Option<Token>, String),
+    /// Contains the throw keyword and the value thrown by lox code
+    Thrown(Token, Value),
+    /// Contains the import keyword and the path of a module that doesn't exist
+    ModuleNotFound(Token, String),
+    /// Contains the import keyword and the files that import each other, in import order
+    ImportCycle(Token, Vec<String>),
+    /// Contains the import keyword and the path of a module that had errors reported
+    InvalidModule(Token, String),
+}
+
+impl RunTimeError {
+    /// Creates an error for a native function to return
+    pub fn native(msg: impl Into<String>) -> Self {
+        Self::NativeError(None, msg.into())
+    }
+
+    /// The token the error was raised at
+    pub fn token(&self) -> Option<&Token> {
+        match self {
+            Self::InvalidOperand(token, _)
+            | Self::InvalidOperands(token, _)
+            | Self::DivisionByZero(token)
+            | Self::IntegerOverflow(token)
+            | Self::InvalidIndex(token, _)
+            | Self::NotIndexable(token, _)
+            | Self::UndefinedKey(token, _)
+            | Self::InvalidKey(token, _)
+            | Self::NotIterable(token, _)
+            | Self::Thrown(token, _)
+            | Self::ModuleNotFound(token, _)
+            | Self::ImportCycle(token, _)
+            | Self::InvalidModule(token, _)
+            | Self::UndefinedVariable(token)
+            | Self::AssignToBuiltin(token)
+            | Self::ArityMismatch(token, _, _)
+            | Self::NotCallable(token, _)
+            | Self::UndefinedProperty(token)
+            | Self::NotAnInstance(token, _)
+            | Self::SuperclassNotClass(token) => Some(token),
+            Self::NativeError(token, _) => token.as_ref(),
+        }
+    }
+
+    /// Places errors that don't know where they were raised at the given token
+    pub(crate) fn or_at(self, token: &Token) -> Self {
+        match self {
+            Self::NativeError(None, msg) => Self::NativeError(Some(token.clone()), msg),
+            err => err,
+        }
+    }
+}
+
+impl std::fmt::Display for RunTimeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InvalidOperand(_, msg) | Self::InvalidOperands(_, msg) => write!(f, "{msg}"),
+            Self::DivisionByZero(token) => {
+                write!(f, "Right operand of '{}' can't be zero", token.lexeme)
+            }
+            Self::IntegerOverflow(token) => write!(f, "Integer overflow in '{}'", token.lexeme),
+            Self::InvalidIndex(_, msg) | Self::NotIndexable(_, msg) | Self::NotIterable(_, msg) => {
+                write!(f, "{msg}")
+            }
+            Self::UndefinedKey(_, key) => write!(f, "Undefined key '{key}'"),
+            Self::InvalidKey(_, msg) => write!(f, "{msg}"),
+            Self::UndefinedVariable(token) => write!(f, "Undefined variable '{}'", token.lexeme),
+            Self::AssignToBuiltin(token) => write!(
+                f,
+                "Can't assign to builtin '{}'; shadow it with var",
+                token.lexeme
+            ),
+            Self::ArityMismatch(_, expected, received) => {
+                write!(f, "Expected {expected} arguments but got {received}")
+            }
+            Self::NotCallable(_, msg) => write!(f, "{msg}"),
+            Self::UndefinedProperty(token) => write!(f, "Undefined property '{}'", token.lexeme),
+            Self::NotAnInstance(_, msg) => write!(f, "{msg}"),
+            Self::SuperclassNotClass(token) => {
+                write!(f, "Superclass '{}' must be a class", token.lexeme)
+            }
+            Self::NativeError(_, msg) => write!(f, "{msg}"),
+            Self::Thrown(_, value) => write!(f, "Uncaught exception: {value}"),
+            Self::ModuleNotFound(_, path) => write!(f, "Can't find module '{path}'"),
+            Self::ImportCycle(_, files) => write!(f, "Import cycle: {}", files.join(" -> ")),
+            Self::InvalidModule(_, path) => write!(f, "Can't import '{path}', it has errors"),
+        }
+    }
 }
@@ -1,23 +1,373 @@
+use std::{
+    cell::RefCell,
+    cmp::Ordering,
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    rc::Rc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
 use crate::{
     ast_enum::{
-        AssignNode, AstNodeAccept, BinaryNode, ExprAcceptMut, ExprNode, ExprVisitorMut,
-        GroupingNode, LiteralNode, LogicalNode, UnaryNode,
+        AssignNode, AssignTarget, BinaryNode, CallNode, CompoundAssignNode, ExprAcceptMut,
+        ExprNode, ExprVisitorMut, GetNode, GroupingNode, IndexNode, IndexSetNode, LiteralNode,
+        LogicalNode, MapNode, SetNode, SuperNode, TernaryNode, UnaryNode, UpdateNode, VariableNode,
+    },
+    callable::{LoxCallable, LoxFunction, NativeFn, NativeFunction},
+    class::{LoxClass, LoxInstance},
+    enum_stmt::{
+        BlockNode, ClassNode, ForInNode, FunctionNode, IfNode, ImportNode, MatchNode, ReturnNode,
+        StmtAcceptorMut, StmtNode, StmtVisitorMut, ThrowNode, TryNode, VarNode, WhileNode,
     },
-    enum_stmt::{BlockNode, IfNode, StmtAcceptorMut, StmtNode, StmtVisitorMut, VarNode, WhileNode},
     environment::Environment,
+    module::LoxModule,
     token::{Token, TokenType},
     RunTimeError,
 };
 
-#[derive(Debug)]
 pub struct Interpreter {
-    envrionment: Environment,
+    /// The natives and classes every module can use, encloses the globals of each module
+    builtins: Rc<RefCell<Environment>>,
+    /// The outermost scope of the module being executed, variables the resolver couldn't find
+    /// are looked up here
+    globals: Rc<RefCell<Environment>>,
+    /// The innermost scope of the code being executed
+    environment: Rc<RefCell<Environment>>,
+    /// The class of the values errors raised by the interpreter are caught as
+    error_class: Rc<LoxClass>,
+    /// Every module that finished running, by canonical path
+    modules: HashMap<PathBuf, Rc<LoxModule>>,
+    /// The canonical and the reported path of the files being run, the innermost import last
+    loading: Vec<(PathBuf, String)>,
+}
+
+/// Environments can hold the functions that close over them, so only the loaded modules are
+/// printed
+impl std::fmt::Debug for Interpreter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Interpreter")
+            .field("modules", &self.modules.keys().collect::<Vec<_>>())
+            .finish_non_exhaustive()
+    }
 }
 
 impl Interpreter {
     pub fn new() -> Self {
-        Self {
-            envrionment: Environment::new(),
+        let builtins = Rc::new(RefCell::new(Environment::new()));
+        let error_class = Rc::new(LoxClass::new("Error".to_string(), None, HashMap::new()));
+        builtins
+            .borrow_mut()
+            .put("Error".to_string(), Value::Class(Rc::clone(&error_class)));
+        let globals = Rc::new(RefCell::new(Environment::enclosing(Rc::clone(&builtins))));
+
+        let mut interpreter = Self {
+            builtins,
+            environment: Rc::clone(&globals),
+            globals,
+            error_class,
+            modules: HashMap::new(),
+            loading: Vec::new(),
+        };
+
+        interpreter.define_native("clock", 0, |_| {
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_err(|err| RunTimeError::native(err.to_string()))?;
+            Ok(Value::Number(now.as_secs_f64()))
+        });
+        interpreter
+    }
+
+    /// Defines a global function that calls back into rust
+    ///
+    /// The interpreter checks the number of arguments matches the arity before calling it
+    pub fn define_native<F>(&mut self, name: &str, arity: usize, function: F)
+    where
+        F: Fn(&[Value]) -> Result<Value, RunTimeError> + 'static,
+    {
+        let function: Box<NativeFn> = Box::new(function);
+        let native = NativeFunction::new(name.to_string(), arity, function);
+        self.builtins
+            .borrow_mut()
+            .put(name.to_string(), Value::Callable(Rc::new(native)));
+    }
+
+    fn look_up_variable(&self, name: &Token, depth: Option<usize>) -> Result<Value, RunTimeError> {
+        match depth {
+            Some(distance) => self
+                .environment
+                .borrow()
+                .get_at(distance, &name.lexeme)
+                .ok_or_else(|| RunTimeError::UndefinedVariable(name.clone())),
+            None => self.globals.borrow().get(name),
+        }
+    }
+
+    fn assign_variable(
+        &mut self,
+        name: &Token,
+        depth: Option<usize>,
+        value: Value,
+    ) -> Result<(), RunTimeError> {
+        match depth {
+            Some(distance) => self
+                .environment
+                .borrow_mut()
+                .assign_at(distance, name, value),
+            None => {
+                // Builtins are shared by every module, so they can be shadowed but not reassigned
+                let mut globals = self.globals.borrow_mut();
+                if globals.get_local(&name.lexeme).is_none()
+                    && self.builtins.borrow().get_local(&name.lexeme).is_some()
+                {
+                    return Err(RunTimeError::AssignToBuiltin(name.clone()));
+                }
+                globals.assign_at(0, name, value)
+            }
+        }
+    }
+
+    /// Replaces the value stored at the target with the result of `modify`
+    ///
+    /// The target, including the object of a property, is only evaluated once. Returns the old
+    /// and the new value
+    fn modify_target<F>(
+        &mut self,
+        target: &AssignTarget,
+        modify: F,
+    ) -> Result<(Value, Value), RunTimeError>
+    where
+        F: FnOnce(&mut Self, Value) -> Result<Value, RunTimeError>,
+    {
+        match target {
+            AssignTarget::Variable(variable) => {
+                let old = self.look_up_variable(&variable.name, variable.depth.get())?;
+                let new = modify(self, old.clone())?;
+                self.assign_variable(&variable.name, variable.depth.get(), new.clone())?;
+                Ok((old, new))
+            }
+            AssignTarget::Property(get) => {
+                let Value::Instance(instance) = get.object.accept_mut(self)? else {
+                    return Err(RunTimeError::NotAnInstance(
+                        get.name.clone(),
+                        "Only instances have fields".to_string(),
+                    ));
+                };
+                let old = LoxInstance::get(&instance, &get.name)?;
+                let new = modify(self, old.clone())?;
+                instance.borrow_mut().set(&get.name, new.clone());
+                Ok((old, new))
+            }
+            AssignTarget::Index(node) => {
+                let object = node.object.accept_mut(self)?;
+                let index = node.index.accept_mut(self)?;
+                let old = self.get_index(&node.bracket, &object, &index)?;
+                let new = modify(self, old.clone())?;
+                self.set_index(&node.bracket, &object, &index, new.clone())?;
+                Ok((old, new))
+            }
+        }
+    }
+
+    /// Reads `object[index]`
+    fn get_index(
+        &self,
+        bracket: &Token,
+        object: &Value,
+        index: &Value,
+    ) -> Result<Value, RunTimeError> {
+        match object {
+            Value::List(list) => {
+                let list = list.borrow();
+                let i = list_index(bracket, list.len(), index)?;
+                Ok(list[i].clone())
+            }
+            Value::Map(map) => {
+                let key = map_key(bracket, index)?;
+                map.borrow()
+                    .get(&key)
+                    .cloned()
+                    .ok_or_else(|| RunTimeError::UndefinedKey(bracket.clone(), key.to_string()))
+            }
+            object => Err(RunTimeError::NotIndexable(
+                bracket.clone(),
+                format!("Only lists and maps can be indexed, received {object}"),
+            )),
+        }
+    }
+
+    /// Writes `object[index] = value`
+    fn set_index(
+        &self,
+        bracket: &Token,
+        object: &Value,
+        index: &Value,
+        value: Value,
+    ) -> Result<(), RunTimeError> {
+        match object {
+            Value::List(list) => {
+                let mut list = list.borrow_mut();
+                let i = list_index(bracket, list.len(), index)?;
+                list[i] = value;
+                Ok(())
+            }
+            Value::Map(map) => {
+                let key = map_key(bracket, index)?;
+                let _ = map.borrow_mut().insert(key, value);
+                Ok(())
+            }
+            object => Err(RunTimeError::NotIndexable(
+                bracket.clone(),
+                format!("Only lists and maps can be indexed, received {object}"),
+            )),
+        }
+    }
+
+    /// Applies an arithmetic or comparison operator, compound assignments use the operator
+    /// they are built on
+    fn binary_operation(
+        &self,
+        operator: &Token,
+        left: Value,
+        right: Value,
+    ) -> Result<Value, RunTimeError> {
+        let numbers_only = |left: &Value, right: &Value| {
+            RunTimeError::InvalidOperands(
+                operator.clone(),
+                format!("Operands must be numbers, received {left} and {right}"),
+            )
+        };
+
+        match operator.t_type {
+            TokenType::Plus | TokenType::PlusEqual => match (left, right) {
+                (Value::String(l), Value::String(r)) => Ok(Value::String(format!("{l}{r}"))),
+                (l, r) => match NumberPair::new(&l, &r) {
+                    Some(NumberPair::Integers(l, r)) => checked(operator, l.checked_add(r)),
+                    Some(NumberPair::Floats(l, r)) => Ok(Value::Number(l + r)),
+                    None => Err(RunTimeError::InvalidOperands(
+                        operator.clone(),
+                        format!(
+                            "Operands must be two numbers or two strings, received {l} and {r}"
+                        ),
+                    )),
+                },
+            },
+            TokenType::Minus | TokenType::MinusEqual => match NumberPair::new(&left, &right) {
+                Some(NumberPair::Integers(l, r)) => checked(operator, l.checked_sub(r)),
+                Some(NumberPair::Floats(l, r)) => Ok(Value::Number(l - r)),
+                None => Err(numbers_only(&left, &right)),
+            },
+            TokenType::Star | TokenType::StarEqual => match NumberPair::new(&left, &right) {
+                Some(NumberPair::Integers(l, r)) => checked(operator, l.checked_mul(r)),
+                Some(NumberPair::Floats(l, r)) => Ok(Value::Number(l * r)),
+                None => Err(numbers_only(&left, &right)),
+            },
+            // Always a float division, `~/` divides integers
+            TokenType::Slash | TokenType::SlashEqual => match NumberPair::new(&left, &right) {
+                Some(NumberPair::Integers(l, r)) => Ok(Value::Number(l as f64 / r as f64)),
+                Some(NumberPair::Floats(l, r)) => Ok(Value::Number(l / r)),
+                None => Err(numbers_only(&left, &right)),
+            },
+            // Floored so the result takes the sign of the divisor and `a == (a ~/ b) * b + a % b`
+            TokenType::Percent => match NumberPair::new(&left, &right) {
+                Some(NumberPair::Integers(_, 0)) | Some(NumberPair::Floats(_, 0.0)) => {
+                    Err(RunTimeError::DivisionByZero(operator.clone()))
+                }
+                Some(NumberPair::Integers(l, r)) => {
+                    // Only i64::MIN % -1 wraps, and its remainder is 0 either way. Adding the
+                    // divisor can't overflow as it only happens when the signs differ
+                    let rem = l.wrapping_rem(r);
+                    if rem != 0 && (rem < 0) != (r < 0) {
+                        Ok(Value::Integer(rem + r))
+                    } else {
+                        Ok(Value::Integer(rem))
+                    }
+                }
+                Some(NumberPair::Floats(l, r)) => Ok(Value::Number(l - r * (l / r).floor())),
+                None => Err(numbers_only(&left, &right)),
+            },
+            TokenType::TildeSlash => match NumberPair::new(&left, &right) {
+                Some(NumberPair::Integers(_, 0)) | Some(NumberPair::Floats(_, 0.0)) => {
+                    Err(RunTimeError::DivisionByZero(operator.clone()))
+                }
+                Some(NumberPair::Integers(l, r)) => {
+                    let quotient = l.checked_div(r).map(|q| {
+                        if l % r != 0 && (l < 0) != (r < 0) {
+                            q - 1
+                        } else {
+                            q
+                        }
+                    });
+                    checked(operator, quotient)
+                }
+                Some(NumberPair::Floats(l, r)) => Ok(Value::Number((l / r).floor())),
+                None => Err(numbers_only(&left, &right)),
+            },
+            // An integer to a negative power is a float. A negative base with a fractional power
+            // is NaN, as in `powf`
+            TokenType::StarStar => match NumberPair::new(&left, &right) {
+                Some(NumberPair::Integers(l, r)) if r < 0 => {
+                    Ok(Value::Number((l as f64).powf(r as f64)))
+                }
+                Some(NumberPair::Integers(l, r)) => checked(
+                    operator,
+                    u32::try_from(r).ok().and_then(|r| l.checked_pow(r)),
+                ),
+                Some(NumberPair::Floats(l, r)) => Ok(Value::Number(l.powf(r))),
+                None => Err(numbers_only(&left, &right)),
+            },
+            TokenType::Less => match numeric_order(&left, &right) {
+                Some(ordering) => Ok(Value::Bool(ordering.is_some_and(Ordering::is_lt))),
+                None => Err(numbers_only(&left, &right)),
+            },
+            TokenType::LessEqual => match numeric_order(&left, &right) {
+                Some(ordering) => Ok(Value::Bool(ordering.is_some_and(Ordering::is_le))),
+                None => Err(numbers_only(&left, &right)),
+            },
+            TokenType::Greater => match numeric_order(&left, &right) {
+                Some(ordering) => Ok(Value::Bool(ordering.is_some_and(Ordering::is_gt))),
+                None => Err(numbers_only(&left, &right)),
+            },
+            TokenType::GreaterEqual => match numeric_order(&left, &right) {
+                Some(ordering) => Ok(Value::Bool(ordering.is_some_and(Ordering::is_ge))),
+                None => Err(numbers_only(&left, &right)),
+            },
+            TokenType::DotDot => match (left, right) {
+                (Value::Integer(start), Value::Integer(end)) => Ok(Value::Range(start, end)),
+                (l, r) => Err(RunTimeError::InvalidOperands(
+                    operator.clone(),
+                    format!("Range bounds must be integers, received {l} and {r}"),
+                )),
+            },
+            TokenType::In => match right {
+                // Values that can't be keys are never equal to a key
+                Value::Map(map) => Ok(Value::Bool(
+                    MapKey::from_value(&left).is_some_and(|key| map.borrow().contains_key(&key)),
+                )),
+                Value::List(list) => Ok(Value::Bool(
+                    list.borrow()
+                        .iter()
+                        .any(|element| self.is_equal(&left, element)),
+                )),
+                Value::Range(start, end) => {
+                    let n = match left {
+                        Value::Integer(i) => Some(i),
+                        Value::Number(n) => float_to_integer(n),
+                        _ => None,
+                    };
+                    Ok(Value::Bool(n.is_some_and(|n| (start..end).contains(&n))))
+                }
+                right => Err(RunTimeError::InvalidOperands(
+                    operator.clone(),
+                    format!(
+                        "Right operand of 'in' must be a list, a map or a range, received {right}"
+                    ),
+                )),
+            },
+            TokenType::EqualEqual => Ok(Value::Bool(self.is_equal(&left, &right))),
+            TokenType::BangEqual => Ok(Value::Bool(!self.is_equal(&left, &right))),
+            _ => unimplemented!("Binary operatror not matched"),
         }
     }
 
@@ -33,36 +383,251 @@ impl Interpreter {
     fn is_equal(&self, left: &Value, right: &Value) -> bool {
         match (left, right) {
             (Value::Number(l), Value::Number(r)) => l == r,
+            (Value::Integer(l), Value::Integer(r)) => l == r,
+            (Value::Integer(i), Value::Number(n)) | (Value::Number(n), Value::Integer(i)) => {
+                float_to_integer(*n) == Some(*i)
+            }
             (Value::String(l), Value::String(r)) => l == r,
             (Value::Bool(l), Value::Bool(r)) => l == r,
             (Value::Nil, Value::Nil) => true,
+            (Value::Callable(l), Value::Callable(r)) => Rc::ptr_eq(l, r),
+            (Value::Class(l), Value::Class(r)) => Rc::ptr_eq(l, r),
+            (Value::Instance(l), Value::Instance(r)) => Rc::ptr_eq(l, r),
+            (Value::Module(l), Value::Module(r)) => Rc::ptr_eq(l, r),
+            (Value::List(l), Value::List(r)) => Rc::ptr_eq(l, r),
+            (Value::Map(l), Value::Map(r)) => Rc::ptr_eq(l, r),
+            (Value::Range(l_start, l_end), Value::Range(r_start, r_end)) => {
+                l_start == r_start && l_end == r_end
+            }
             _ => false,
         }
     }
 
     pub fn interpret(&mut self, stmts: Vec<StmtNode>) -> Result<(), RunTimeError> {
         for stmt in stmts {
-            self.execute(&stmt)?;
+            match self.execute(&stmt) {
+                Ok(()) => {}
+                Err(Unwind::Error(err)) => return Err(err),
+                Err(Unwind::Return(_)) => {
+                    unreachable!("Parser rejects return statements outside of functions")
+                }
+                Err(Unwind::Break | Unwind::Continue) => {
+                    unreachable!("Parser rejects loop control outside of loops")
+                }
+            }
         }
         Ok(())
     }
 
-    fn execute(&mut self, stmt: &StmtNode) -> Result<(), RunTimeError> {
+    /// Interprets a program read from a file, its imports are relative to the file
+    pub fn interpret_file(&mut self, stmts: Vec<StmtNode>, file: &str) -> Result<(), RunTimeError> {
+        // A file that can't be canonicalized can't be imported either, so it can't be in a cycle
+        let path = fs::canonicalize(file).unwrap_or_else(|_| PathBuf::from(file));
+        self.loading.push((path, file.to_string()));
+        let result = self.interpret(stmts);
+        let _ = self.loading.pop();
+        result
+    }
+
+    /// Runs the module the import keyword refers to, or returns it if it has already been run
+    ///
+    /// The path is relative to the file the import is in, or the working directory for code
+    /// typed into the prompt. Each module runs with its own globals
+    fn import(&mut self, keyword: &Token, path: &str) -> Result<Rc<LoxModule>, RunTimeError> {
+        let path = match keyword.file.as_deref().and_then(|f| Path::new(f).parent()) {
+            Some(directory) => directory.join(path),
+            None => PathBuf::from(path),
+        };
+        let file = path.to_string_lossy().to_string();
+        let (Ok(canonical), Ok(source)) = (fs::canonicalize(&path), fs::read_to_string(&path))
+        else {
+            return Err(RunTimeError::ModuleNotFound(keyword.clone(), file));
+        };
+
+        if let Some(module) = self.modules.get(&canonical) {
+            return Ok(Rc::clone(module));
+        }
+        if let Some(start) = self.loading.iter().position(|(p, _)| *p == canonical) {
+            let mut files: Vec<String> = self.loading[start..]
+                .iter()
+                .map(|(_, file)| file.clone())
+                .collect();
+            files.push(file);
+            return Err(RunTimeError::ImportCycle(keyword.clone(), files));
+        }
+
+        // Errors are reported as the module is compiled, naming the module's file
+        let compiled = crate::compile(source, Some(file.as_str().into()));
+        let (Some(stmts), false) = (compiled.program, compiled.had_error) else {
+            return Err(RunTimeError::InvalidModule(keyword.clone(), file));
+        };
+
+        let globals = Rc::new(RefCell::new(Environment::enclosing(Rc::clone(
+            &self.builtins,
+        ))));
+        let previous_globals = std::mem::replace(&mut self.globals, Rc::clone(&globals));
+        let previous_environment = std::mem::replace(&mut self.environment, Rc::clone(&globals));
+        self.loading.push((canonical.clone(), file));
+        let result = self.interpret(stmts);
+        let _ = self.loading.pop();
+        self.globals = previous_globals;
+        self.environment = previous_environment;
+        result?;
+
+        let name = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let module = Rc::new(LoxModule::new(name, globals));
+        let _ = self.modules.insert(canonical, Rc::clone(&module));
+        Ok(module)
+    }
+
+    /// The value a catch clause binds for an error
+    ///
+    /// Thrown values are caught as they are, errors raised by the interpreter become an
+    /// `Error` instance with the message and the line the error was raised on
+    fn caught_value(&self, err: RunTimeError) -> Value {
+        if let RunTimeError::Thrown(_, value) = err {
+            return value;
+        }
+
+        let mut instance = LoxInstance::new(Rc::clone(&self.error_class));
+        instance.set_field("message", Value::String(err.to_string()));
+        let line = match err.token() {
+            Some(token) => Value::Integer(token.line as i64),
+            None => Value::Nil,
+        };
+        instance.set_field("line", line);
+        Value::Instance(Rc::new(RefCell::new(instance)))
+    }
+
+    fn execute(&mut self, stmt: &StmtNode) -> Result<(), Unwind> {
         stmt.accept(self)
     }
+
+    /// Executes the statements inside of the given environment
+    ///
+    /// The previous environment is restored once the statements are done, even if one of them
+    /// fails
+    pub fn execute_block(
+        &mut self,
+        stmts: &[StmtNode],
+        environment: Environment,
+    ) -> Result<(), Unwind> {
+        let previous = std::mem::replace(&mut self.environment, Rc::new(RefCell::new(environment)));
+        let result = stmts.iter().try_for_each(|stmt| self.execute(stmt));
+        self.environment = previous;
+        result
+    }
+
+    /// Executes the statements of a function declared in the module with the given globals
+    ///
+    /// Functions called from another module still see the globals of the module they were
+    /// declared in
+    pub fn execute_in_module(
+        &mut self,
+        stmts: &[StmtNode],
+        environment: Environment,
+        globals: &Rc<RefCell<Environment>>,
+    ) -> Result<(), Unwind> {
+        let previous = std::mem::replace(&mut self.globals, Rc::clone(globals));
+        let result = self.execute_block(stmts, environment);
+        self.globals = previous;
+        result
+    }
+}
+
+/// Stops the execution of statements and unwinds the stack until it is handled
+#[derive(Debug)]
+pub enum Unwind {
+    /// An error that aborts the program
+    Error(RunTimeError),
+    /// A return statement, unwinds to the call site of the function
+    Return(Value),
+    /// A break statement, unwinds to the innermost loop and exits it
+    Break,
+    /// A continue statement, unwinds to the innermost loop and starts the next iteration
+    Continue,
+}
+
+impl From<RunTimeError> for Unwind {
+    fn from(err: RunTimeError) -> Self {
+        Self::Error(err)
+    }
 }
 
 impl StmtVisitorMut for Interpreter {
-    fn visit_while(&mut self, while_stmt: &WhileNode) -> Result<(), RunTimeError> {
+    type Output = Result<(), Unwind>;
+
+    fn visit_return(&mut self, return_node: &ReturnNode) -> Self::Output {
+        let value = match &return_node.value {
+            Some(expr) => expr.accept_mut(self)?,
+            None => Value::Nil,
+        };
+        Err(Unwind::Return(value))
+    }
+
+    fn visit_while(&mut self, while_stmt: &WhileNode) -> Self::Output {
         let mut cond_res = while_stmt.condition.accept_mut(self)?;
         while self.is_truthy(&cond_res) {
-            while_stmt.body.accept(self)?;
+            match while_stmt.body.accept(self) {
+                Ok(()) | Err(Unwind::Continue) => {}
+                Err(Unwind::Break) => break,
+                Err(unwind) => return Err(unwind),
+            }
+
+            if let Some(increment) = &while_stmt.increment {
+                increment.accept_mut(self)?;
+            }
             cond_res = while_stmt.condition.accept_mut(self)?;
         }
         Ok(())
     }
 
-    fn visit_if(&mut self, if_stmt: &IfNode) -> Result<(), RunTimeError> {
+    fn visit_for_in(&mut self, for_in: &ForInNode) -> Self::Output {
+        let iterable = for_in.iterable.accept_mut(self)?;
+        let items = iterate(&for_in.keyword, iterable)?;
+
+        for item in items {
+            // A fresh scope each time so closures capture that iteration's item
+            let mut environment = Environment::enclosing(Rc::clone(&self.environment));
+            environment.put(for_in.name.lexeme.clone(), item);
+            match self.execute_block(std::slice::from_ref(&*for_in.body), environment) {
+                Ok(()) | Err(Unwind::Continue) => {}
+                Err(Unwind::Break) => break,
+                Err(unwind) => return Err(unwind),
+            }
+        }
+        Ok(())
+    }
+
+    fn visit_do_while(&mut self, while_stmt: &WhileNode) -> Self::Output {
+        loop {
+            match while_stmt.body.accept(self) {
+                // Continue skips to the condition, it doesn't restart the body unchecked
+                Ok(()) | Err(Unwind::Continue) => {}
+                Err(Unwind::Break) => break,
+                Err(unwind) => return Err(unwind),
+            }
+
+            let cond_res = while_stmt.condition.accept_mut(self)?;
+            if !self.is_truthy(&cond_res) {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    fn visit_break(&mut self, _keyword: &Token) -> Self::Output {
+        Err(Unwind::Break)
+    }
+
+    fn visit_continue(&mut self, _keyword: &Token) -> Self::Output {
+        Err(Unwind::Continue)
+    }
+
+    fn visit_if(&mut self, if_stmt: &IfNode) -> Self::Output {
         let condition_res = if_stmt.condition.accept_mut(self)?;
         if self.is_truthy(&condition_res) {
             if_stmt.then_branch.accept(self)?;
@@ -74,38 +639,152 @@ impl StmtVisitorMut for Interpreter {
         Ok(())
     }
 
-    fn visit_expr(&mut self, expr_node: &ExprNode) -> Result<(), RunTimeError> {
-        let _ = expr_node.accept_mut(self);
+    fn visit_expr(&mut self, expr_node: &ExprNode) -> Self::Output {
+        let _ = expr_node.accept_mut(self)?;
         Ok(())
     }
 
-    fn visit_print(&mut self, expr_node: &ExprNode) -> Result<(), RunTimeError> {
-        let eval = expr_node.accept_mut(self).unwrap();
+    fn visit_print(&mut self, expr_node: &ExprNode) -> Self::Output {
+        let eval = expr_node.accept_mut(self)?;
         // replace with stdout
         println!("{}", eval);
         Ok(())
     }
 
-    fn visit_var_dec(&mut self, var_node: &VarNode) -> Result<(), RunTimeError> {
+    fn visit_var_dec(&mut self, var_node: &VarNode) -> Self::Output {
         let name = var_node.name.lexeme.clone();
 
         let value = var_node.value_expr.accept_mut(self)?;
-        self.envrionment.put(name, value);
+        self.environment.borrow_mut().put(name, value);
+        Ok(())
+    }
+
+    fn visit_import(&mut self, import_node: &ImportNode) -> Self::Output {
+        let module = self.import(&import_node.keyword, &import_node.path)?;
+        self.environment
+            .borrow_mut()
+            .put(import_node.name.lexeme.clone(), Value::Module(module));
+        Ok(())
+    }
+
+    fn visit_throw(&mut self, throw_node: &ThrowNode) -> Self::Output {
+        let value = throw_node.value.accept_mut(self)?;
+        Err(Unwind::Error(RunTimeError::Thrown(
+            throw_node.keyword.clone(),
+            value,
+        )))
+    }
+
+    fn visit_try(&mut self, try_node: &TryNode) -> Self::Output {
+        let environment = Environment::enclosing(Rc::clone(&self.environment));
+        let result = match (
+            self.execute_block(&try_node.body, environment),
+            &try_node.catch,
+        ) {
+            (Err(Unwind::Error(err)), Some(catch)) => {
+                let mut environment = Environment::enclosing(Rc::clone(&self.environment));
+                environment.put(catch.name.lexeme.clone(), self.caught_value(err));
+                self.execute_block(&catch.body, environment)
+            }
+            (result, _) => result,
+        };
+
+        // Runs for errors, returns and loop control too. If the finally block unwinds itself
+        // that replaces whatever the body was doing
+        if let Some(finally) = &try_node.finally {
+            let environment = Environment::enclosing(Rc::clone(&self.environment));
+            self.execute_block(finally, environment)?;
+        }
+        result
+    }
+
+    fn visit_match(&mut self, match_node: &MatchNode) -> Self::Output {
+        let subject = match_node.subject.accept_mut(self)?;
+
+        let mut matched = None;
+        'arms: for arm in match_node.arms.iter() {
+            for pattern in arm.patterns.iter() {
+                let value = pattern.accept_mut(self)?;
+                if self.is_equal(&subject, &value) {
+                    matched = Some(&arm.body);
+                    break 'arms;
+                }
+            }
+        }
+
+        match matched.or(match_node.default.as_ref()) {
+            Some(body) => {
+                let environment = Environment::enclosing(Rc::clone(&self.environment));
+                self.execute_block(body, environment)
+            }
+            None => Ok(()),
+        }
+    }
+
+    fn visit_block(&mut self, block_node: &BlockNode) -> Self::Output {
+        let environment = Environment::enclosing(Rc::clone(&self.environment));
+        self.execute_block(&block_node.0, environment)
+    }
+
+    fn visit_fun_dec(&mut self, fun_node: &Rc<FunctionNode>) -> Self::Output {
+        let function = LoxFunction::new(
+            Rc::clone(fun_node),
+            Rc::clone(&self.environment),
+            Rc::clone(&self.globals),
+            false,
+        );
+        self.environment.borrow_mut().put(
+            fun_node.name.lexeme.clone(),
+            Value::Callable(Rc::new(function)),
+        );
         Ok(())
     }
 
-    fn visit_block(&mut self, block_node: &BlockNode) -> Result<(), RunTimeError> {
-        self.envrionment = Environment::enclosing(self.envrionment.clone());
-        for stmt in block_node.0.iter() {
-            stmt.accept(self)?;
+    fn visit_class_dec(&mut self, class_node: &ClassNode) -> Self::Output {
+        let superclass = match &class_node.superclass {
+            Some(superclass) => match self.visit_variable(superclass)? {
+                Value::Class(class) => Some(class),
+                _ => return Err(RunTimeError::SuperclassNotClass(superclass.name.clone()).into()),
+            },
+            None => None,
+        };
+
+        // Methods of subclasses close over an extra scope that holds the superclass
+        let enclosing = superclass.as_ref().map(|superclass| {
+            let mut environment = Environment::enclosing(Rc::clone(&self.environment));
+            environment.put("super".to_string(), Value::Class(Rc::clone(superclass)));
+            std::mem::replace(&mut self.environment, Rc::new(RefCell::new(environment)))
+        });
+
+        let methods = class_node
+            .methods
+            .iter()
+            .map(|method| {
+                let is_initializer = method.name.lexeme == "init";
+                let function = LoxFunction::new(
+                    Rc::clone(method),
+                    Rc::clone(&self.environment),
+                    Rc::clone(&self.globals),
+                    is_initializer,
+                );
+                (method.name.lexeme.clone(), Rc::new(function))
+            })
+            .collect();
+
+        if let Some(enclosing) = enclosing {
+            self.environment = enclosing;
         }
-        self.envrionment = self.envrionment.parent().unwrap().as_ref().clone();
+
+        let class = LoxClass::new(class_node.name.lexeme.clone(), superclass, methods);
+        self.environment
+            .borrow_mut()
+            .put(class_node.name.lexeme.clone(), Value::Class(Rc::new(class)));
         Ok(())
     }
 
-    // fn visit_var_assign(&mut self, var_node: &VarNode) -> Result<(), RunTimeError> {
+    // fn visit_var_assign(&mut self, var_node: &VarNode) -> Self::Output {
     //    let value = var_node.value_expr.accept_mut(self)?;
-    //    self.envrionment.update( &var_node.name, value)?;
+    //    self.environment.update( &var_node.name, value)?;
     //    Ok(())
     // }
 }
@@ -113,8 +792,116 @@ impl StmtVisitorMut for Interpreter {
 impl ExprVisitorMut for Interpreter {
     type Output = Result<Value, RunTimeError>;
 
+    fn visit_call(&mut self, node: &CallNode) -> Self::Output {
+        let callee = node.callee.accept_mut(self)?;
+
+        let mut arguments = Vec::with_capacity(node.arguments.len());
+        for argument in node.arguments.iter() {
+            arguments.push(argument.accept_mut(self)?);
+        }
+
+        let function: Rc<dyn LoxCallable> = match callee {
+            Value::Callable(function) => function,
+            Value::Class(class) => class,
+            _ => {
+                return Err(RunTimeError::NotCallable(
+                    node.paren.clone(),
+                    format!("Can only call functions and classes, received {callee}"),
+                ))
+            }
+        };
+
+        if arguments.len() != function.arity() {
+            return Err(RunTimeError::ArityMismatch(
+                node.paren.clone(),
+                function.arity(),
+                arguments.len(),
+            ));
+        }
+
+        function
+            .call(self, arguments)
+            .map_err(|err| err.or_at(&node.paren))
+    }
+
+    fn visit_get(&mut self, node: &GetNode) -> Self::Output {
+        match node.object.accept_mut(self)? {
+            Value::Instance(instance) => LoxInstance::get(&instance, &node.name),
+            Value::Module(module) => module.get(&node.name),
+            object => Err(RunTimeError::NotAnInstance(
+                node.name.clone(),
+                format!("Only instances have properties, received {object}"),
+            )),
+        }
+    }
+
+    fn visit_set(&mut self, node: &SetNode) -> Self::Output {
+        let Value::Instance(instance) = node.object.accept_mut(self)? else {
+            return Err(RunTimeError::NotAnInstance(
+                node.name.clone(),
+                "Only instances have fields".to_string(),
+            ));
+        };
+
+        let value = node.value.accept_mut(self)?;
+        instance.borrow_mut().set(&node.name, value.clone());
+        Ok(value)
+    }
+
+    fn visit_this(&mut self, node: &VariableNode) -> Self::Output {
+        self.look_up_variable(&node.name, node.depth.get())
+    }
+
+    fn visit_super(&mut self, node: &SuperNode) -> Self::Output {
+        let distance = node
+            .depth
+            .get()
+            .expect("Resolver always resolves super in methods");
+
+        // The scope binding this is always directly inside the scope binding super
+        let superclass = self.environment.borrow().get_at(distance, "super");
+        let instance = self.environment.borrow().get_at(distance - 1, "this");
+        let (Some(Value::Class(superclass)), Some(Value::Instance(instance))) =
+            (superclass, instance)
+        else {
+            unreachable!("super is only used in methods that are bound to an instance")
+        };
+
+        match superclass.find_method(&node.method.lexeme) {
+            Some(method) => Ok(Value::Callable(Rc::new(method.bind(instance)))),
+            None => Err(RunTimeError::UndefinedProperty(node.method.clone())),
+        }
+    }
+
+    fn visit_lambda(&mut self, node: &Rc<FunctionNode>) -> Self::Output {
+        let function = LoxFunction::new(
+            Rc::clone(node),
+            Rc::clone(&self.environment),
+            Rc::clone(&self.globals),
+            false,
+        );
+        Ok(Value::Callable(Rc::new(function)))
+    }
+
+    fn visit_sequence(&mut self, exprs: &[ExprNode]) -> Self::Output {
+        let mut value = Value::Nil;
+        for expr in exprs {
+            value = expr.accept_mut(self)?;
+        }
+        Ok(value)
+    }
+
+    fn visit_ternary(&mut self, node: &TernaryNode) -> Self::Output {
+        let condition = node.condition.accept_mut(self)?;
+        if self.is_truthy(&condition) {
+            node.then_branch.accept_mut(self)
+        } else {
+            node.else_branch.accept_mut(self)
+        }
+    }
+
     fn visit_logical(&mut self, node: &LogicalNode) -> Self::Output {
-        let mut left_value = node.left.accept_mut(self)?;
+        let left_value = node.left.accept_mut(self)?;
         let left_is_truthy = self.is_truthy(&left_value);
 
         if node.operator.t_type == TokenType::Or {
@@ -131,59 +918,85 @@ impl ExprVisitorMut for Interpreter {
     }
 
     fn visit_assign(&mut self, node: &AssignNode) -> Self::Output {
-        // Look up variable value and return it
         let v = node.value.accept_mut(self)?;
-        self.envrionment.assign(&node.name, v.clone())?;
+        self.assign_variable(&node.name, node.depth.get(), v.clone())?;
         Ok(v)
     }
 
-    fn visit_variable(&mut self, value: &Token) -> Self::Output {
+    fn visit_compound_assign(&mut self, node: &CompoundAssignNode) -> Self::Output {
+        let (_, new) = self.modify_target(&node.target, |interpreter, old| {
+            let value = node.value.accept_mut(interpreter)?;
+            interpreter.binary_operation(&node.operator, old, value)
+        })?;
+        Ok(new)
+    }
+
+    fn visit_update(&mut self, node: &UpdateNode) -> Self::Output {
+        let delta = match node.operator.t_type {
+            TokenType::PlusPlus => 1,
+            TokenType::MinusMinus => -1,
+            _ => panic!("Parser placed a none update operator in an update node {node:?}"),
+        };
+        let (old, new) = self.modify_target(&node.target, |_, old| match old {
+            Value::Integer(i) => checked(&node.operator, i.checked_add(delta)),
+            Value::Number(n) => Ok(Value::Number(n + delta as f64)),
+            v => Err(RunTimeError::InvalidOperand(
+                node.operator.clone(),
+                format!("Operand must be a number, received {v}"),
+            )),
+        })?;
+        Ok(if node.prefix { new } else { old })
+    }
+
+    fn visit_list(&mut self, elements: &[ExprNode]) -> Self::Output {
+        let values = elements
+            .iter()
+            .map(|element| element.accept_mut(self))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Value::List(Rc::new(RefCell::new(values))))
+    }
+
+    fn visit_map(&mut self, node: &MapNode) -> Self::Output {
+        let mut map = HashMap::new();
+        for (key, value) in node.entries.iter() {
+            let key = map_key(&node.brace, &key.accept_mut(self)?)?;
+            let value = value.accept_mut(self)?;
+            let _ = map.insert(key, value);
+        }
+        Ok(Value::Map(Rc::new(RefCell::new(map))))
+    }
+
+    fn visit_interpolation(&mut self, parts: &[ExprNode]) -> Self::Output {
+        let mut string = String::new();
+        for part in parts {
+            string.push_str(&part.accept_mut(self)?.to_string());
+        }
+        Ok(Value::String(string))
+    }
+
+    fn visit_index(&mut self, node: &IndexNode) -> Self::Output {
+        let object = node.object.accept_mut(self)?;
+        let index = node.index.accept_mut(self)?;
+        self.get_index(&node.bracket, &object, &index)
+    }
+
+    fn visit_index_set(&mut self, node: &IndexSetNode) -> Self::Output {
+        let object = node.object.accept_mut(self)?;
+        let index = node.index.accept_mut(self)?;
+        let value = node.value.accept_mut(self)?;
+        self.set_index(&node.bracket, &object, &index, value.clone())?;
+        Ok(value)
+    }
+
+    fn visit_variable(&mut self, node: &VariableNode) -> Self::Output {
         // Look up variable value and return it
-        self.envrionment.get(value)
+        self.look_up_variable(&node.name, node.depth.get())
     }
 
     fn visit_binary(&mut self, value: &BinaryNode) -> Self::Output {
         let left_eval = value.left.accept_mut(self)?;
         let right_eval = value.right.accept_mut(self)?;
-
-        match value.operator.t_type {
-            TokenType::Plus => match (left_eval, right_eval) {
-                (Value::Number(l), Value::Number(r)) => Ok(Value::Number(l + r)),
-                (Value::String(l), Value::String(r)) => Ok(Value::String(format!("{l}{r}"))),
-                _ => panic!("Addition not defined"),
-            },
-            TokenType::Minus => match (left_eval, right_eval) {
-                (Value::Number(l), Value::Number(r)) => Ok(Value::Number(l - r)),
-                _ => panic!("Addition not defined"),
-            },
-            TokenType::Star => match (left_eval, right_eval) {
-                (Value::Number(l), Value::Number(r)) => Ok(Value::Number(l * r)),
-                _ => panic!("Addition not defined"),
-            },
-            TokenType::Slash => match (left_eval, right_eval) {
-                (Value::Number(l), Value::Number(r)) => Ok(Value::Number(l / r)),
-                _ => panic!("Addition not defined"),
-            },
-            TokenType::Less => match (left_eval, right_eval) {
-                (Value::Number(l), Value::Number(r)) => Ok(Value::Bool(l < r)),
-                _ => panic!("Addition not defined"),
-            },
-            TokenType::LessEqual => match (left_eval, right_eval) {
-                (Value::Number(l), Value::Number(r)) => Ok(Value::Bool(l <= r)),
-                _ => panic!("Addition not defined"),
-            },
-            TokenType::Greater => match (left_eval, right_eval) {
-                (Value::Number(l), Value::Number(r)) => Ok(Value::Bool(l > r)),
-                _ => panic!("Addition not defined"),
-            },
-            TokenType::GreaterEqual => match (left_eval, right_eval) {
-                (Value::Number(l), Value::Number(r)) => Ok(Value::Bool(l >= r)),
-                _ => panic!("Addition not defined"),
-            },
-            TokenType::EqualEqual => Ok(Value::Bool(self.is_equal(&left_eval, &right_eval))),
-            TokenType::BangEqual => Ok(Value::Bool(!self.is_equal(&left_eval, &right_eval))),
-            _ => unimplemented!("Binary operatror not matched"),
-        }
+        self.binary_operation(&value.operator, left_eval, right_eval)
     }
 
     fn visit_unary(&mut self, value: &UnaryNode) -> Self::Output {
@@ -191,10 +1004,11 @@ impl ExprVisitorMut for Interpreter {
         match value.operator.t_type {
             TokenType::Bang => Ok(Value::Bool(self.is_truthy(&eval))),
             TokenType::Minus => match eval {
+                Value::Integer(i) => checked(&value.operator, i.checked_neg()),
                 Value::Number(n) => Ok(Value::Number(-n)),
-                v => Err(RunTimeError::InvalidBangValue(
+                v => Err(RunTimeError::InvalidOperand(
                     value.operator.clone(),
-                    format!("Received {v:?}"),
+                    format!("Operand must be a number, received {v}"),
                 )),
             },
             _ => {
@@ -210,6 +1024,7 @@ impl ExprVisitorMut for Interpreter {
     fn visit_literal(&mut self, value: &LiteralNode) -> Self::Output {
         match value {
             LiteralNode::String(s) => Ok(Value::String(s.clone())),
+            LiteralNode::Integer(i) => Ok(Value::Integer(*i)),
             LiteralNode::Number(n) => Ok(Value::Number(*n)),
             LiteralNode::True => Ok(Value::Bool(true)),
             LiteralNode::False => Ok(Value::Bool(false)),
@@ -218,20 +1033,312 @@ impl ExprVisitorMut for Interpreter {
     }
 }
 
+/// The items a for in loop visits
+///
+/// Lists are copied up front so the body can change them without affecting the loop, maps
+/// visit their keys in sorted order and strings visit each character
+fn iterate(
+    keyword: &Token,
+    iterable: Value,
+) -> Result<Box<dyn Iterator<Item = Value>>, RunTimeError> {
+    match iterable {
+        Value::Range(start, end) => Ok(Box::new((start..end).map(Value::Integer))),
+        Value::String(s) => {
+            let chars: Vec<_> = s.chars().map(|c| Value::String(c.to_string())).collect();
+            Ok(Box::new(chars.into_iter()))
+        }
+        Value::List(list) => Ok(Box::new(list.borrow().clone().into_iter())),
+        Value::Map(map) => {
+            let mut keys: Vec<_> = map.borrow().keys().cloned().collect();
+            keys.sort();
+            Ok(Box::new(keys.into_iter().map(Value::from)))
+        }
+        value => Err(RunTimeError::NotIterable(
+            keyword.clone(),
+            format!("Can only iterate over ranges, strings, lists and maps, received {value}"),
+        )),
+    }
+}
+
+/// Two numeric operands, promoted to floats unless both are integers
+enum NumberPair {
+    Integers(i64, i64),
+    Floats(f64, f64),
+}
+
+impl NumberPair {
+    fn new(left: &Value, right: &Value) -> Option<Self> {
+        match (left, right) {
+            (Value::Integer(l), Value::Integer(r)) => Some(Self::Integers(*l, *r)),
+            (Value::Integer(l), Value::Number(r)) => Some(Self::Floats(*l as f64, *r)),
+            (Value::Number(l), Value::Integer(r)) => Some(Self::Floats(*l, *r as f64)),
+            (Value::Number(l), Value::Number(r)) => Some(Self::Floats(*l, *r)),
+            _ => None,
+        }
+    }
+}
+
+/// Wraps the result of checked integer arithmetic, `None` means it overflowed
+fn checked(operator: &Token, result: Option<i64>) -> Result<Value, RunTimeError> {
+    result
+        .map(Value::Integer)
+        .ok_or_else(|| RunTimeError::IntegerOverflow(operator.clone()))
+}
+
+/// 2^63, the floats converted to integers must be below it and at least its negation
+///
+/// Unlike i64::MAX it is exactly representable as a float
+const INTEGER_LIMIT: f64 = 9_223_372_036_854_775_808.0;
+
+/// Orders two numbers by their exact values, `None` if either of them isn't a number
+///
+/// The inner `None` means the numbers are unordered because one of them is NaN
+fn numeric_order(left: &Value, right: &Value) -> Option<Option<Ordering>> {
+    match (left, right) {
+        (Value::Integer(l), Value::Integer(r)) => Some(Some(l.cmp(r))),
+        (Value::Number(l), Value::Number(r)) => Some(l.partial_cmp(r)),
+        (Value::Integer(i), Value::Number(n)) => Some(integer_float_order(*i, *n)),
+        (Value::Number(n), Value::Integer(i)) => {
+            Some(integer_float_order(*i, *n).map(Ordering::reverse))
+        }
+        _ => None,
+    }
+}
+
+/// Orders an integer and a float without rounding the integer to a float
+///
+/// Compares against the float rounded down instead, which is exact whenever it is in range
+fn integer_float_order(i: i64, n: f64) -> Option<Ordering> {
+    if n.is_nan() {
+        return None;
+    }
+    if n >= INTEGER_LIMIT {
+        return Some(Ordering::Less);
+    }
+    if n < -INTEGER_LIMIT {
+        return Some(Ordering::Greater);
+    }
+
+    let floor = n.floor();
+    match i.cmp(&(floor as i64)) {
+        // The integer is the whole part of the float, so any fraction makes the float larger
+        Ordering::Equal if n > floor => Some(Ordering::Less),
+        ordering => Some(ordering),
+    }
+}
+
+/// The integer with exactly the same value as the float, if there is one
+fn float_to_integer(n: f64) -> Option<i64> {
+    (n.fract() == 0.0 && (-INTEGER_LIMIT..INTEGER_LIMIT).contains(&n)).then_some(n as i64)
+}
+
+/// Checks that an index is an integer within a list of the given length
+fn list_index(bracket: &Token, len: usize, index: &Value) -> Result<usize, RunTimeError> {
+    match index {
+        Value::Integer(i) => usize::try_from(*i)
+            .ok()
+            .filter(|i| *i < len)
+            .ok_or_else(|| {
+                RunTimeError::InvalidIndex(
+                    bracket.clone(),
+                    format!("Index {i} is out of range for a list of length {len}"),
+                )
+            }),
+        index => Err(RunTimeError::InvalidIndex(
+            bracket.clone(),
+            format!("List indices must be integers, received {index}"),
+        )),
+    }
+}
+
+/// Converts a value into a map key, failing for values that can't be keys
+fn map_key(token: &Token, value: &Value) -> Result<MapKey, RunTimeError> {
+    MapKey::from_value(value).ok_or_else(|| {
+        RunTimeError::InvalidKey(
+            token.clone(),
+            format!("Map keys must be strings, numbers or booleans, received {value}"),
+        )
+    })
+}
+
+/// A value that can be used as a map key
+///
+/// Two keys are equal exactly when their values are equal under `Interpreter::is_equal`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum MapKey {
+    Bool(bool),
+    /// Also holds floats with an integer value, so `1` and `1.0` are the same key
+    Integer(i64),
+    /// The bits of a float that isn't an integer
+    Number(u64),
+    String(String),
+}
+
+impl MapKey {
+    /// NaN is never equal to itself so it can't be found again and isn't a valid key
+    pub fn from_value(value: &Value) -> Option<Self> {
+        match value {
+            Value::Bool(b) => Some(Self::Bool(*b)),
+            Value::Integer(i) => Some(Self::Integer(*i)),
+            Value::Number(n) if n.is_nan() => None,
+            Value::Number(n) => match float_to_integer(*n) {
+                Some(i) => Some(Self::Integer(i)),
+                None => Some(Self::Number(n.to_bits())),
+            },
+            Value::String(s) => Some(Self::String(s.clone())),
+            _ => None,
+        }
+    }
+}
+
+impl Ord for MapKey {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (Self::Bool(l), Self::Bool(r)) => l.cmp(r),
+            (Self::Integer(l), Self::Integer(r)) => l.cmp(r),
+            (Self::String(l), Self::String(r)) => l.cmp(r),
+            _ => match (self.number(), other.number()) {
+                (Some(l), Some(r)) => l.total_cmp(&r),
+                _ => self.rank().cmp(&other.rank()),
+            },
+        }
+    }
+}
+
+impl MapKey {
+    fn number(&self) -> Option<f64> {
+        match self {
+            Self::Integer(i) => Some(*i as f64),
+            Self::Number(n) => Some(f64::from_bits(*n)),
+            _ => None,
+        }
+    }
+
+    /// Booleans sort before numbers, which sort before strings
+    fn rank(&self) -> u8 {
+        match self {
+            Self::Bool(_) => 0,
+            Self::Integer(_) | Self::Number(_) => 1,
+            Self::String(_) => 2,
+        }
+    }
+}
+
+impl From<MapKey> for Value {
+    fn from(key: MapKey) -> Self {
+        match key {
+            MapKey::Bool(b) => Value::Bool(b),
+            MapKey::Integer(i) => Value::Integer(i),
+            MapKey::Number(n) => Value::Number(f64::from_bits(n)),
+            MapKey::String(s) => Value::String(s),
+        }
+    }
+}
+
+impl PartialOrd for MapKey {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl std::fmt::Display for MapKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Bool(b) => write!(f, "{b}"),
+            Self::Integer(i) => write!(f, "{i}"),
+            Self::Number(n) => write!(f, "{}", f64::from_bits(*n)),
+            Self::String(s) => write!(f, "{s}"),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum Value {
+    Integer(i64),
+    /// A float, arithmetic mixing it with an integer produces a float
     Number(f64),
     Bool(bool),
     String(String),
+    Callable(Rc<dyn LoxCallable>),
+    Class(Rc<LoxClass>),
+    Instance(Rc<RefCell<LoxInstance>>),
+    Module(Rc<LoxModule>),
+    /// Shared so every alias of a list sees its mutations
+    List(Rc<RefCell<Vec<Value>>>),
+    Map(Rc<RefCell<HashMap<MapKey, Value>>>),
+    /// The integers from the start up to but not including the end
+    Range(i64, i64),
     Nil,
 }
 
+thread_local! {
+    /// The lists and maps being displayed, from the outermost to the innermost
+    static DISPLAYING: RefCell<Vec<*const ()>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Displays the list or map at the address, or the placeholder if it is already being displayed
+///
+/// Lists and maps can contain themselves, which would otherwise be displayed forever
+fn display_once<F>(
+    f: &mut std::fmt::Formatter<'_>,
+    address: *const (),
+    placeholder: &str,
+    display: F,
+) -> std::fmt::Result
+where
+    F: FnOnce(&mut std::fmt::Formatter<'_>) -> std::fmt::Result,
+{
+    if DISPLAYING.with(|displaying| displaying.borrow().contains(&address)) {
+        return write!(f, "{placeholder}");
+    }
+
+    DISPLAYING.with(|displaying| displaying.borrow_mut().push(address));
+    let result = display(f);
+    DISPLAYING.with(|displaying| {
+        let _ = displaying.borrow_mut().pop();
+    });
+    result
+}
+
 impl std::fmt::Display for Value {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Value::Number(n) => write!(f, "{n}"),
+            Value::Integer(i) => write!(f, "{i}"),
+            // Debug keeps the decimal part of whole floats so they can be told apart from
+            // integers, and switches to an exponent for very large or small ones
+            Value::Number(n) => write!(f, "{n:?}"),
             Value::Bool(b) => write!(f, "{b}"),
             Value::String(s) => write!(f, "{s}"),
+            Value::Callable(c) => write!(f, "{c}"),
+            Value::Class(c) => write!(f, "{c}"),
+            Value::Instance(i) => write!(f, "{}", i.borrow()),
+            Value::Module(m) => write!(f, "{m}"),
+            Value::List(l) => display_once(f, Rc::as_ptr(l).cast(), "[...]", |f| {
+                write!(f, "[")?;
+                for (i, value) in l.borrow().iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{value}")?;
+                }
+                write!(f, "]")
+            }),
+            Value::Map(m) => display_once(f, Rc::as_ptr(m).cast(), "{...}", |f| {
+                // Sorted by key so printing a map is deterministic
+                let map = m.borrow();
+                let mut entries: Vec<_> = map.iter().collect();
+                entries.sort_by_key(|(key, _)| *key);
+
+                write!(f, "{{")?;
+                for (i, (key, value)) in entries.into_iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{key}: {value}")?;
+                }
+                write!(f, "}}")
+            }),
+            Value::Range(start, end) => write!(f, "{start}..{end}"),
             Value::Nil => write!(f, "nil"),
         }
     }
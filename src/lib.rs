@@ -5,26 +5,30 @@ mod scanner;
 mod token;
 // pub mod parser;
 mod ast_enum;
+mod callable;
+mod class;
 pub mod enum_parser;
 mod enum_stmt;
 mod environment;
 pub mod error;
 mod interpreter;
+mod module;
+mod resolver;
 // mod reverse_polish_notation_visitor;
 
 use std::{
     fs,
     io::{self, Write},
     process,
+    rc::Rc,
 };
 
 pub use error::{RunTimeError, SyntaxError};
+pub use interpreter::{MapKey, Value};
 use scanner::{Scanner, ScannerError};
 
 use crate::{
-    ast_enum::{AstNodeAccept, ExprAcceptMut, PrettyPrinter},
-    enum_parser::Parser,
-    interpreter::Interpreter,
+    enum_parser::Parser, enum_stmt::StmtNode, interpreter::Interpreter, resolver::Resolver,
 };
 // pub use reverse_polish_notation_visitor::Rpn;
 
@@ -35,6 +39,12 @@ pub struct Lox {
     interpreter: Interpreter,
 }
 
+impl Default for Lox {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Lox {
     pub fn new() -> Self {
         Self {
@@ -44,8 +54,8 @@ impl Lox {
     }
 
     pub fn run_file(&mut self, path: String) {
-        let source = fs::read_to_string(path).unwrap();
-        self.run(source);
+        let source = fs::read_to_string(&path).unwrap();
+        self.run(source, Some(path.into()));
         if self.had_error {
             process::exit(65)
         }
@@ -61,58 +71,138 @@ impl Lox {
             stdout.flush().unwrap();
 
             let _bytes_read = stdin.read_line(&mut line).unwrap();
-            self.run(line.clone());
+            self.run(line.clone(), None);
             self.had_error = false;
             // Remove contents from the buffer ther have been processed
             line.clear();
         }
     }
 
-    fn run(&mut self, source: String) {
-        let mut scanner = Scanner::new(source);
-        let (tokens, errors) = scanner.scan_tokens();
-        self.had_error = !errors.is_empty();
-
-        for err in errors {
-            match err {
-                ScannerError::UnrecognizedSymbol(line, char) => {
-                    self.error(line, format!("unreconized character: {char}"))
-                }
-                ScannerError::UnterminatedString(line) => self.error(
-                    line,
-                    format!("Unmatch string literal started. Expected closing '\"'"),
-                ),
-            }
-        }
-
-        let mut parser = Parser::new(tokens);
-        let Ok(ast) = parser.parse() else {
-            self.had_error = true;
+    fn run(&mut self, source: String, file: Option<Rc<str>>) {
+        let compiled = compile(source, file.clone());
+        self.had_error = compiled.had_error;
+        let Some(ast) = compiled.program else {
             return;
         };
 
-        // let pretty_print = PrettyPrinter {};
-        // pretty_print.print(&ast);
-
         // Use persistent interpreter to maintain state accross parses
-        if let Err(output) = self.interpreter.interpret(ast) {
+        let result = match file {
+            Some(file) => self.interpreter.interpret_file(ast, &file),
+            None => self.interpreter.interpret(ast),
+        };
+        if let Err(err) = result {
+            let (file, line, location) = match err.token() {
+                Some(token) => (
+                    token.file.clone(),
+                    token.line,
+                    format!("at '{}'", token.lexeme),
+                ),
+                None => (None, 0, "".to_string()),
+            };
+            report(file.as_deref(), line, &location, &err.to_string());
             self.had_error = true;
-            println!("{output:?}");
         }
     }
 
+    /// Registers a rust function as a global that lox code can call
+    ///
+    /// Natives receive their arguments as values and can fail with
+    /// [`RunTimeError::native`], which is reported like any other runtime error
+    pub fn define_native<F>(&mut self, name: &str, arity: usize, function: F)
+    where
+        F: Fn(&[Value]) -> Result<Value, RunTimeError> + 'static,
+    {
+        self.interpreter.define_native(name, arity, function);
+    }
+
     pub fn error(&mut self, line: u64, msg: String) {
-        self.report(line, "".to_string(), msg)
+        report(None, line, "", &msg);
+        self.had_error = true;
+    }
+}
+
+/// A program that has been scanned, parsed and resolved
+pub(crate) struct Compiled {
+    /// Missing when the source couldn't be parsed or resolved
+    pub program: Option<Vec<StmtNode>>,
+    /// Whether any error was reported, a program is still run after scanner errors
+    pub had_error: bool,
+}
+
+/// Turns the source into a program the interpreter can run, reporting every error found
+pub(crate) fn compile(source: String, file: Option<Rc<str>>) -> Compiled {
+    let mut scanner = Scanner::new(source, file.clone());
+    let (tokens, errors) = scanner.scan_tokens();
+    let had_error = !errors.is_empty();
+
+    for err in errors {
+        let (line, msg) = match err {
+            ScannerError::UnrecognizedSymbol(line, char) => {
+                (line, format!("unreconized character: {char}"))
+            }
+            ScannerError::UnterminatedString(line) => (
+                line,
+                "Unmatch string literal started. Expected closing '\"'".to_string(),
+            ),
+            ScannerError::UnterminatedInterpolation(line) => (
+                line,
+                "Unterminated string interpolation. Expected '}' to close '${'".to_string(),
+            ),
+            ScannerError::EmptyInterpolation(line) => (
+                line,
+                "Empty string interpolation. Expected an expression inside '${}'".to_string(),
+            ),
+            ScannerError::InvalidEscape(line, escape) => (
+                line,
+                format!("Invalid escape sequence '{escape}' in string"),
+            ),
+            ScannerError::InvalidNumber(line, lexeme) => {
+                (line, format!("Invalid number literal '{lexeme}'"))
+            }
+        };
+        report(file.as_deref(), line, "", &msg);
     }
 
-    fn report(&mut self, line: u64, location: String, msg: String) {
-        let mut stderr = io::stderr();
-        stderr
-            .write_all(format!("[line {line}] Error {location}: {msg}\n").as_bytes())
-            .unwrap();
-        stderr.flush().unwrap();
+    let mut parser = Parser::new(tokens);
+    let Ok(ast) = parser.parse() else {
+        return Compiled {
+            program: None,
+            had_error: true,
+        };
+    };
 
-        // Maybe move to the error function?
-        self.had_error = true;
+    if let Err(errors) = Resolver::new().resolve(&ast) {
+        for err in errors {
+            let token = err.token();
+            let location = format!("around '{}'", token.lexeme);
+            report(
+                token.file.as_deref(),
+                token.line,
+                &location,
+                &err.to_string(),
+            );
+        }
+        return Compiled {
+            program: None,
+            had_error: true,
+        };
     }
+
+    Compiled {
+        program: Some(ast),
+        had_error,
+    }
+}
+
+/// Writes an error to stderr, naming the file it was found in when there is one
+pub(crate) fn report(file: Option<&str>, line: u64, location: &str, msg: &str) {
+    let position = match file {
+        Some(file) => format!("{file}, line {line}"),
+        None => format!("line {line}"),
+    };
+    let mut stderr = io::stderr();
+    stderr
+        .write_all(format!("[{position}] Error {location}: {msg}\n").as_bytes())
+        .unwrap();
+    stderr.flush().unwrap();
 }
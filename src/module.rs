@@ -0,0 +1,46 @@
+use std::{cell::RefCell, fmt, rc::Rc};
+
+use crate::{environment::Environment, interpreter::Value, token::Token, RunTimeError};
+
+/// A file loaded with `import`, its top-level variables are read as properties
+///
+/// Names starting with an underscore are private to the module
+pub struct LoxModule {
+    name: String,
+    /// The outermost scope of the module, shared with the functions declared in it
+    globals: Rc<RefCell<Environment>>,
+}
+
+impl LoxModule {
+    pub fn new(name: String, globals: Rc<RefCell<Environment>>) -> Self {
+        Self { name, globals }
+    }
+
+    /// Gets a variable the module exports
+    ///
+    /// Reads the current value, so changes made by the module's own functions are visible
+    pub fn get(&self, name: &Token) -> Result<Value, RunTimeError> {
+        if name.lexeme.starts_with('_') {
+            return Err(RunTimeError::UndefinedProperty(name.clone()));
+        }
+        self.globals
+            .borrow()
+            .get_local(&name.lexeme)
+            .ok_or_else(|| RunTimeError::UndefinedProperty(name.clone()))
+    }
+}
+
+/// The globals hold functions that close over them, so only the name is printed
+impl fmt::Debug for LoxModule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LoxModule")
+            .field("name", &self.name)
+            .finish_non_exhaustive()
+    }
+}
+
+impl fmt::Display for LoxModule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<module {}>", self.name)
+    }
+}
@@ -0,0 +1,375 @@
+use std::{cell::Cell, collections::HashMap, rc::Rc};
+
+use crate::{
+    ast_enum::{
+        AssignNode, AssignTarget, BinaryNode, CallNode, CompoundAssignNode, ExprAcceptMut,
+        ExprNode, ExprVisitorMut, GetNode, GroupingNode, IndexNode, IndexSetNode, LiteralNode,
+        LogicalNode, MapNode, SetNode, SuperNode, TernaryNode, UnaryNode, UpdateNode, VariableNode,
+    },
+    enum_stmt::{
+        BlockNode, ClassNode, ForInNode, FunctionNode, IfNode, ImportNode, MatchNode, ReturnNode,
+        StmtAcceptorMut, StmtNode, StmtVisitorMut, ThrowNode, TryNode, VarNode, WhileNode,
+    },
+    token::Token,
+    SyntaxError,
+};
+
+/// A static pass that runs between the parser and the interpreter
+///
+/// Works out how many scopes sit between each variable reference and its declaration so the
+/// interpreter doesn't depend on the dynamic shape of the environment chain. Globals are left
+/// unresolved and are looked up by name.
+#[derive(Debug, Default)]
+pub struct Resolver {
+    /// The local scopes enclosing the current node. A variable maps to true once its
+    /// initializer has been resolved
+    scopes: Vec<HashMap<String, bool>>,
+    errors: Vec<SyntaxError>,
+}
+
+impl Resolver {
+    pub fn new() -> Self {
+        Self {
+            scopes: Vec::new(),
+            errors: Vec::new(),
+        }
+    }
+
+    /// Resolves all the statements, returning every error that was found
+    pub fn resolve(mut self, stmts: &[StmtNode]) -> Result<(), Vec<SyntaxError>> {
+        self.resolve_stmts(stmts);
+        if self.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(self.errors)
+        }
+    }
+
+    fn resolve_stmts(&mut self, stmts: &[StmtNode]) {
+        for stmt in stmts {
+            stmt.accept(self);
+        }
+    }
+
+    fn begin_scope(&mut self) {
+        self.scopes.push(HashMap::new());
+    }
+
+    fn end_scope(&mut self) {
+        let _ = self.scopes.pop();
+    }
+
+    fn declare(&mut self, name: &Token) {
+        let Some(scope) = self.scopes.last_mut() else {
+            return;
+        };
+
+        if scope.insert(name.lexeme.clone(), false).is_some() {
+            self.errors.push(SyntaxError::AlreadyDeclared(name.clone()));
+        }
+    }
+
+    fn define(&mut self, name: &Token) {
+        if let Some(scope) = self.scopes.last_mut() {
+            let _ = scope.insert(name.lexeme.clone(), true);
+        }
+    }
+
+    /// Defines a name the interpreter creates itself, such as this and super
+    fn define_implicit(&mut self, name: &str) {
+        if let Some(scope) = self.scopes.last_mut() {
+            let _ = scope.insert(name.to_string(), true);
+        }
+    }
+
+    /// Stores the distance to the closest scope that declares the name
+    fn resolve_local(&mut self, name: &str, depth: &Cell<Option<usize>>) {
+        let distance = self
+            .scopes
+            .iter()
+            .rev()
+            .position(|scope| scope.contains_key(name));
+        depth.set(distance);
+    }
+
+    fn resolve_function(&mut self, function: &FunctionNode) {
+        self.begin_scope();
+        for param in function.params.iter() {
+            self.declare(param);
+            self.define(param);
+        }
+        self.resolve_stmts(&function.body);
+        self.end_scope();
+    }
+
+    fn resolve_target(&mut self, target: &AssignTarget) {
+        match target {
+            AssignTarget::Variable(variable) => self.visit_variable(variable),
+            AssignTarget::Property(get) => self.visit_get(get),
+            AssignTarget::Index(index) => self.visit_index(index),
+        }
+    }
+}
+
+impl StmtVisitorMut for Resolver {
+    type Output = ();
+
+    fn visit_print(&mut self, expr_node: &ExprNode) -> Self::Output {
+        expr_node.accept_mut(self);
+    }
+
+    fn visit_expr(&mut self, expr_node: &ExprNode) -> Self::Output {
+        expr_node.accept_mut(self);
+    }
+
+    fn visit_var_dec(&mut self, var_node: &VarNode) -> Self::Output {
+        self.declare(&var_node.name);
+        var_node.value_expr.accept_mut(self);
+        self.define(&var_node.name);
+    }
+
+    fn visit_block(&mut self, block_node: &BlockNode) -> Self::Output {
+        self.begin_scope();
+        self.resolve_stmts(&block_node.0);
+        self.end_scope();
+    }
+
+    fn visit_if(&mut self, if_stmt: &IfNode) -> Self::Output {
+        if_stmt.condition.accept_mut(self);
+        if_stmt.then_branch.accept(self);
+        if let Some(else_branch) = &if_stmt.else_branch {
+            else_branch.accept(self);
+        }
+    }
+
+    fn visit_while(&mut self, while_stmt: &WhileNode) -> Self::Output {
+        while_stmt.condition.accept_mut(self);
+        while_stmt.body.accept(self);
+        if let Some(increment) = &while_stmt.increment {
+            increment.accept_mut(self);
+        }
+    }
+
+    fn visit_for_in(&mut self, for_in: &ForInNode) -> Self::Output {
+        for_in.iterable.accept_mut(self);
+
+        // Matches the scope the interpreter creates for each iteration
+        self.begin_scope();
+        self.declare(&for_in.name);
+        self.define(&for_in.name);
+        for_in.body.accept(self);
+        self.end_scope();
+    }
+
+    fn visit_do_while(&mut self, while_stmt: &WhileNode) -> Self::Output {
+        while_stmt.body.accept(self);
+        while_stmt.condition.accept_mut(self);
+    }
+
+    fn visit_fun_dec(&mut self, fun_node: &Rc<FunctionNode>) -> Self::Output {
+        // Defined before the body so functions can refer to themselves
+        self.declare(&fun_node.name);
+        self.define(&fun_node.name);
+        self.resolve_function(fun_node);
+    }
+
+    fn visit_return(&mut self, return_node: &ReturnNode) -> Self::Output {
+        if let Some(value) = &return_node.value {
+            value.accept_mut(self);
+        }
+    }
+
+    fn visit_class_dec(&mut self, class_node: &ClassNode) -> Self::Output {
+        self.declare(&class_node.name);
+        self.define(&class_node.name);
+
+        if let Some(superclass) = &class_node.superclass {
+            self.visit_variable(superclass);
+            self.begin_scope();
+            self.define_implicit("super");
+        }
+
+        self.begin_scope();
+        self.define_implicit("this");
+        for method in class_node.methods.iter() {
+            self.resolve_function(method);
+        }
+        self.end_scope();
+
+        if class_node.superclass.is_some() {
+            self.end_scope();
+        }
+    }
+
+    fn visit_break(&mut self, _keyword: &Token) -> Self::Output {}
+
+    fn visit_continue(&mut self, _keyword: &Token) -> Self::Output {}
+
+    fn visit_throw(&mut self, throw_node: &ThrowNode) -> Self::Output {
+        throw_node.value.accept_mut(self);
+    }
+
+    fn visit_try(&mut self, try_node: &TryNode) -> Self::Output {
+        self.begin_scope();
+        self.resolve_stmts(&try_node.body);
+        self.end_scope();
+
+        if let Some(catch) = &try_node.catch {
+            self.begin_scope();
+            self.declare(&catch.name);
+            self.define(&catch.name);
+            self.resolve_stmts(&catch.body);
+            self.end_scope();
+        }
+
+        if let Some(finally) = &try_node.finally {
+            self.begin_scope();
+            self.resolve_stmts(finally);
+            self.end_scope();
+        }
+    }
+
+    fn visit_import(&mut self, import_node: &ImportNode) -> Self::Output {
+        self.declare(&import_node.name);
+        self.define(&import_node.name);
+    }
+
+    fn visit_match(&mut self, match_node: &MatchNode) -> Self::Output {
+        match_node.subject.accept_mut(self);
+        for arm in match_node.arms.iter() {
+            for pattern in arm.patterns.iter() {
+                pattern.accept_mut(self);
+            }
+            self.begin_scope();
+            self.resolve_stmts(&arm.body);
+            self.end_scope();
+        }
+        if let Some(default) = &match_node.default {
+            self.begin_scope();
+            self.resolve_stmts(default);
+            self.end_scope();
+        }
+    }
+}
+
+impl ExprVisitorMut for Resolver {
+    type Output = ();
+
+    fn visit_literal(&mut self, _value: &LiteralNode) -> Self::Output {}
+
+    fn visit_unary(&mut self, value: &UnaryNode) -> Self::Output {
+        value.right.accept_mut(self);
+    }
+
+    fn visit_binary(&mut self, value: &BinaryNode) -> Self::Output {
+        value.left.accept_mut(self);
+        value.right.accept_mut(self);
+    }
+
+    fn visit_grouping(&mut self, value: &GroupingNode) -> Self::Output {
+        value.inner.accept_mut(self);
+    }
+
+    fn visit_variable(&mut self, node: &VariableNode) -> Self::Output {
+        let declared_not_defined = self
+            .scopes
+            .last()
+            .and_then(|scope| scope.get(&node.name.lexeme))
+            .is_some_and(|defined| !defined);
+        if declared_not_defined {
+            self.errors
+                .push(SyntaxError::ReadInOwnInitializer(node.name.clone()));
+        }
+
+        self.resolve_local(&node.name.lexeme, &node.depth);
+    }
+
+    fn visit_assign(&mut self, node: &AssignNode) -> Self::Output {
+        node.value.accept_mut(self);
+        self.resolve_local(&node.name.lexeme, &node.depth);
+    }
+
+    fn visit_logical(&mut self, node: &LogicalNode) -> Self::Output {
+        node.left.accept_mut(self);
+        node.right.accept_mut(self);
+    }
+
+    fn visit_call(&mut self, node: &CallNode) -> Self::Output {
+        node.callee.accept_mut(self);
+        for argument in node.arguments.iter() {
+            argument.accept_mut(self);
+        }
+    }
+
+    fn visit_get(&mut self, node: &GetNode) -> Self::Output {
+        node.object.accept_mut(self);
+    }
+
+    fn visit_set(&mut self, node: &SetNode) -> Self::Output {
+        node.value.accept_mut(self);
+        node.object.accept_mut(self);
+    }
+
+    fn visit_this(&mut self, node: &VariableNode) -> Self::Output {
+        self.resolve_local(&node.name.lexeme, &node.depth);
+    }
+
+    fn visit_super(&mut self, node: &SuperNode) -> Self::Output {
+        self.resolve_local(&node.keyword.lexeme, &node.depth);
+    }
+
+    fn visit_lambda(&mut self, node: &Rc<FunctionNode>) -> Self::Output {
+        self.resolve_function(node);
+    }
+
+    fn visit_sequence(&mut self, exprs: &[ExprNode]) -> Self::Output {
+        for expr in exprs {
+            expr.accept_mut(self);
+        }
+    }
+
+    fn visit_ternary(&mut self, node: &TernaryNode) -> Self::Output {
+        node.condition.accept_mut(self);
+        node.then_branch.accept_mut(self);
+        node.else_branch.accept_mut(self);
+    }
+
+    fn visit_compound_assign(&mut self, node: &CompoundAssignNode) -> Self::Output {
+        self.resolve_target(&node.target);
+        node.value.accept_mut(self);
+    }
+
+    fn visit_update(&mut self, node: &UpdateNode) -> Self::Output {
+        self.resolve_target(&node.target);
+    }
+
+    fn visit_list(&mut self, elements: &[ExprNode]) -> Self::Output {
+        for element in elements {
+            element.accept_mut(self);
+        }
+    }
+
+    fn visit_map(&mut self, node: &MapNode) -> Self::Output {
+        for (key, value) in node.entries.iter() {
+            key.accept_mut(self);
+            value.accept_mut(self);
+        }
+    }
+
+    fn visit_interpolation(&mut self, parts: &[ExprNode]) -> Self::Output {
+        for part in parts {
+            part.accept_mut(self);
+        }
+    }
+
+    fn visit_index(&mut self, node: &IndexNode) -> Self::Output {
+        node.object.accept_mut(self);
+        node.index.accept_mut(self);
+    }
+
+    fn visit_index_set(&mut self, node: &IndexSetNode) -> Self::Output {
+        node.object.accept_mut(self);
+        node.index.accept_mut(self);
+        node.value.accept_mut(self);
+    }
+}
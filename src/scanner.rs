@@ -1,19 +1,24 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, rc::Rc};
 
 use crate::token::{Token, TokenLiteral, TokenType};
 
 pub struct Scanner {
     source: String,
+    /// The file the source was read from, attached to every token for diagnostics
+    file: Option<Rc<str>>,
     tokens: Vec<Token>,
     errors: Vec<ScannerError>,
     line: u64,
     start: usize,
     current: usize,
     keywords: HashMap<String, TokenType>,
+    /// The line each unclosed interpolation started on and the number of braces opened inside
+    /// it, innermost last
+    interpolations: Vec<(u64, usize)>,
 }
 
 impl Scanner {
-    pub fn new(source: String) -> Self {
+    pub fn new(source: String, file: Option<Rc<str>>) -> Self {
         let tokens = Vec::new();
         let errors = Vec::new();
         let line = 1;
@@ -23,12 +28,14 @@ impl Scanner {
 
         Self {
             source,
+            file,
             tokens,
             errors,
             line,
             start,
             current,
             keywords,
+            interpolations: Vec::new(),
         }
     }
 
@@ -41,11 +48,17 @@ impl Scanner {
             self.scan_token();
         }
 
+        for (line, _) in self.interpolations.drain(..) {
+            self.errors
+                .push(ScannerError::UnterminatedInterpolation(line));
+        }
+
         let eof_token = Token::new(
             TokenType::Eof,
             "".to_string(),
             TokenLiteral::None,
             self.line,
+            self.file.clone(),
         );
         self.tokens.push(eof_token);
 
@@ -57,14 +70,76 @@ impl Scanner {
         match c {
             '(' => self.add_token(TokenType::LeftParen, TokenLiteral::None),
             ')' => self.add_token(TokenType::RightParen, TokenLiteral::None),
-            '{' => self.add_token(TokenType::LeftBrace, TokenLiteral::None),
-            '}' => self.add_token(TokenType::RightBrace, TokenLiteral::None),
+            '{' => {
+                if let Some((_, braces)) = self.interpolations.last_mut() {
+                    *braces += 1;
+                }
+                self.add_token(TokenType::LeftBrace, TokenLiteral::None);
+            }
+            '}' => match self.interpolations.last_mut() {
+                // Closes the interpolation, the rest is the next part of the string
+                Some((_, 0)) => {
+                    let _ = self.interpolations.pop();
+                    let empty = self
+                        .tokens
+                        .last()
+                        .is_some_and(|token| token.t_type == TokenType::Interpolation);
+                    if empty {
+                        self.errors
+                            .push(ScannerError::EmptyInterpolation(self.line));
+                    }
+                    self.tokenize_string_literal();
+                }
+                Some((_, braces)) => {
+                    *braces -= 1;
+                    self.add_token(TokenType::RightBrace, TokenLiteral::None);
+                }
+                None => self.add_token(TokenType::RightBrace, TokenLiteral::None),
+            },
+            '[' => self.add_token(TokenType::LeftBracket, TokenLiteral::None),
+            ']' => self.add_token(TokenType::RightBracket, TokenLiteral::None),
             ',' => self.add_token(TokenType::Comma, TokenLiteral::None),
-            '.' => self.add_token(TokenType::Dot, TokenLiteral::None),
-            '-' => self.add_token(TokenType::Minus, TokenLiteral::None),
-            '+' => self.add_token(TokenType::Plus, TokenLiteral::None),
+            '.' => {
+                if self.match_char('.') {
+                    self.add_token(TokenType::DotDot, TokenLiteral::None);
+                } else {
+                    self.add_token(TokenType::Dot, TokenLiteral::None);
+                }
+            }
+            '-' => {
+                if self.match_char('-') {
+                    self.add_token(TokenType::MinusMinus, TokenLiteral::None);
+                } else if self.match_char('=') {
+                    self.add_token(TokenType::MinusEqual, TokenLiteral::None);
+                } else {
+                    self.add_token(TokenType::Minus, TokenLiteral::None);
+                }
+            }
+            '+' => {
+                if self.match_char('+') {
+                    self.add_token(TokenType::PlusPlus, TokenLiteral::None);
+                } else if self.match_char('=') {
+                    self.add_token(TokenType::PlusEqual, TokenLiteral::None);
+                } else {
+                    self.add_token(TokenType::Plus, TokenLiteral::None);
+                }
+            }
             ';' => self.add_token(TokenType::Semicolon, TokenLiteral::None),
-            '*' => self.add_token(TokenType::Star, TokenLiteral::None),
+            '*' => {
+                if self.match_char('*') {
+                    self.add_token(TokenType::StarStar, TokenLiteral::None);
+                } else if self.match_char('=') {
+                    self.add_token(TokenType::StarEqual, TokenLiteral::None);
+                } else {
+                    self.add_token(TokenType::Star, TokenLiteral::None);
+                }
+            }
+            '%' => self.add_token(TokenType::Percent, TokenLiteral::None),
+            '~' if self.match_char('/') => {
+                self.add_token(TokenType::TildeSlash, TokenLiteral::None)
+            }
+            '?' => self.add_token(TokenType::Question, TokenLiteral::None),
+            ':' => self.add_token(TokenType::Colon, TokenLiteral::None),
             '!' => {
                 if self.match_char('=') {
                     self.add_token(TokenType::BangEqual, TokenLiteral::None);
@@ -95,10 +170,10 @@ impl Scanner {
             }
             '/' => {
                 if self.match_char('/') {
+                    // The newline is left for scan_token to count
                     while self.peek() != '\n' && !self.is_eof() {
                         let _ = self.advance();
                     }
-                    self.line += 1;
                 } else if self.match_char('*') {
                     loop {
                         if self.is_eof() {
@@ -118,6 +193,8 @@ impl Scanner {
 
                         let _ = self.advance();
                     }
+                } else if self.match_char('=') {
+                    self.add_token(TokenType::SlashEqual, TokenLiteral::None);
                 } else {
                     self.add_token(TokenType::Slash, TokenLiteral::None);
                 }
@@ -178,11 +255,33 @@ impl Scanner {
 
     /// Tokenizes string literals
     ///
-    /// String literals can span multiple lines
+    /// String literals can span multiple lines. A `${` ends the current part of the string with
+    /// an interpolation token, the string carries on after the matching `}`
     fn tokenize_string_literal(&mut self) {
+        let mut value = String::new();
+        // The start of the source that hasn't been copied into the value yet
+        let mut segment_start = self.current;
         while self.peek() != '"' && !self.is_eof() {
-            if self.peek() == '\n' {
-                self.line += 1;
+            match self.peek() {
+                '$' if self.peek_next() == '{' => {
+                    value.push_str(self.source.get(segment_start..self.current).unwrap());
+                    let _ = self.advance();
+                    let _ = self.advance();
+                    self.add_token(TokenType::Interpolation, TokenLiteral::String(value));
+                    self.interpolations.push((self.line, 0));
+                    return;
+                }
+                '\\' => {
+                    value.push_str(self.source.get(segment_start..self.current).unwrap());
+                    let _ = self.advance();
+                    if let Some(c) = self.escape_sequence() {
+                        value.push(c);
+                    }
+                    segment_start = self.current;
+                    continue;
+                }
+                '\n' => self.line += 1,
+                _ => {}
             }
             let _ = self.advance();
         }
@@ -193,38 +292,125 @@ impl Scanner {
             return;
         }
 
+        value.push_str(self.source.get(segment_start..self.current).unwrap());
         // Consume the '"'
         let _ = self.advance();
-
-        // Get the stirng without the quotes
-        let value = self
-            .source
-            .get((self.start + 1)..(self.current - 1))
-            .unwrap()
-            .to_string();
         self.add_token(TokenType::String, TokenLiteral::String(value));
     }
 
+    /// Reads the escape sequence after a backslash
+    ///
+    /// Supports `\n`, `\t`, `\r`, `\0`, `\\`, `\"`, `\$` and `\u{XXXX}`. Invalid escapes are
+    /// recorded as errors and leave nothing in the string
+    fn escape_sequence(&mut self) -> Option<char> {
+        // Taken as a whole char so a multi-byte char after the backslash is skipped entirely
+        let c = self.source.get(self.current..)?.chars().next()?;
+        self.current += c.len_utf8();
+
+        match c {
+            'n' => Some('\n'),
+            't' => Some('\t'),
+            'r' => Some('\r'),
+            '0' => Some('\0'),
+            '\\' => Some('\\'),
+            '"' => Some('"'),
+            '$' => Some('$'),
+            'u' => self.unicode_escape(),
+            c => {
+                if c == '\n' {
+                    self.line += 1;
+                }
+                self.errors
+                    .push(ScannerError::InvalidEscape(self.line, format!("\\{c}")));
+                None
+            }
+        }
+    }
+
+    /// Reads the `{XXXX}` of a unicode escape, one to six hex digits naming a unicode scalar
+    fn unicode_escape(&mut self) -> Option<char> {
+        let start = self.current;
+        let mut digits = None;
+        if self.match_char('{') {
+            while self.peek().is_ascii_hexdigit() {
+                let _ = self.advance();
+            }
+            let digits_end = self.current;
+            if self.match_char('}') {
+                digits = self.source.get((start + 1)..digits_end);
+            }
+        }
+
+        let c = digits
+            .filter(|hex| (1..=6).contains(&hex.len()))
+            .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+            .and_then(char::from_u32);
+        if c.is_none() {
+            // Only ascii has been consumed so the slice is on char boundaries
+            let escape = self.source.get(start..self.current).unwrap();
+            self.errors.push(ScannerError::InvalidEscape(
+                self.line,
+                format!("\\u{escape}"),
+            ));
+        }
+        c
+    }
+
     /// Tokenizes number literals.
     ///
-    /// Forms: 42 or 42.24
+    /// Forms: 42, 42.24, 6.02e23, 0xFF or 0b1010. Digits can be seperated by single
+    /// underscores, such as 1_000_000
     fn tokenize_number_literal(&mut self) {
-        while is_digit(self.peek()) {
+        let prefixed = self.source.as_bytes()[self.start] == b'0';
+        let radix = match self.peek() {
+            'x' | 'X' if prefixed => Some(16),
+            'b' | 'B' if prefixed => Some(2),
+            _ => None,
+        };
+
+        match radix {
+            Some(_) => {
+                let _ = self.advance();
+            }
+            None => {
+                self.consume_digits();
+                if self.peek() == '.' && is_digit(self.peek_next()) {
+                    let _ = self.advance();
+                    self.consume_digits();
+                }
+                if matches!(self.peek(), 'e' | 'E') {
+                    let _ = self.advance();
+                    if matches!(self.peek(), '+' | '-') {
+                        let _ = self.advance();
+                    }
+                }
+            }
+        }
+        // Letters stuck to the end belong to the literal so `0xFG` or `12ab` are reported as a
+        // whole instead of becoming a number followed by an identifier
+        while is_alphanumeric(self.peek()) {
             let _ = self.advance();
         }
 
-        if self.peek() == '.' && is_digit(self.peek_next()) {
-            let _ = self.advance();
-            while is_digit(self.peek()) {
-                let _ = self.advance();
+        let lexeme = self.source.get(self.start..self.current).unwrap();
+        let value = match radix {
+            Some(radix) => parse_radix_literal(&lexeme[2..], radix),
+            None => parse_decimal_literal(lexeme),
+        };
+        match value {
+            Some(literal) => self.add_token(TokenType::Number, literal),
+            None => {
+                let lexeme = lexeme.to_string();
+                self.errors
+                    .push(ScannerError::InvalidNumber(self.line, lexeme));
             }
         }
+    }
 
-        let value = self.source.get(self.start..self.current).unwrap();
-        self.add_token(
-            TokenType::Number,
-            TokenLiteral::Number(value.parse().unwrap()),
-        );
+    fn consume_digits(&mut self) {
+        while is_digit(self.peek()) || self.peek() == '_' {
+            let _ = self.advance();
+        }
     }
 
     /// Consumes all the chareacters that are attached to an identifier token
@@ -254,17 +440,70 @@ impl Scanner {
     /// Includes the line of the source code the token is on and the exact lexeme
     fn add_token(&mut self, t_type: TokenType, literal: TokenLiteral) {
         let lexeme = self.source.get(self.start..self.current).unwrap().into();
-        let token = Token::new(t_type, lexeme, literal, self.line);
+        let token = Token::new(t_type, lexeme, literal, self.line, self.file.clone());
         self.tokens.push(token);
     }
 }
 
+/// Parses the integer digits after a `0x` or `0b` prefix
+fn parse_radix_literal(digits: &str, radix: u32) -> Option<TokenLiteral> {
+    if !is_digit_group(digits, radix) {
+        return None;
+    }
+    let n = i64::from_str_radix(&digits.replace('_', ""), radix).ok()?;
+    Some(TokenLiteral::Integer(n))
+}
+
+/// Parses a decimal literal, it is a float if it has a fraction or an exponent
+fn parse_decimal_literal(lexeme: &str) -> Option<TokenLiteral> {
+    let (mantissa, exponent) = match lexeme.split_once(['e', 'E']) {
+        Some((mantissa, exponent)) => (mantissa, Some(exponent)),
+        None => (lexeme, None),
+    };
+    let (integer, fraction) = match mantissa.split_once('.') {
+        Some((integer, fraction)) => (integer, Some(fraction)),
+        None => (mantissa, None),
+    };
+    let exponent = exponent.map(|e| e.strip_prefix(['+', '-']).unwrap_or(e));
+
+    let valid = is_digit_group(integer, 10)
+        && fraction.is_none_or(|f| is_digit_group(f, 10))
+        && exponent.is_none_or(|e| is_digit_group(e, 10));
+    if !valid {
+        return None;
+    }
+
+    let digits = lexeme.replace('_', "");
+    if fraction.is_none() && exponent.is_none() {
+        // Integers too large for 64 bits fail to parse and are reported as invalid, except 2^63
+        // which wraps to i64::MIN so the parser can accept it right after a minus
+        match digits.parse() {
+            Ok(n) => Some(TokenLiteral::Integer(n)),
+            Err(_) if digits.parse() == Ok(i64::MIN.unsigned_abs()) => {
+                Some(TokenLiteral::Integer(i64::MIN))
+            }
+            Err(_) => None,
+        }
+    } else {
+        digits.parse().ok().map(TokenLiteral::Number)
+    }
+}
+
+/// Whether the digits are non-empty, valid in the radix and only seperated by single underscores
+fn is_digit_group(digits: &str, radix: u32) -> bool {
+    !digits.is_empty()
+        && !digits.starts_with('_')
+        && !digits.ends_with('_')
+        && !digits.contains("__")
+        && digits.chars().all(|c| c == '_' || c.is_digit(radix))
+}
+
 fn is_digit(c: char) -> bool {
-    c >= '0' && c <= '9'
+    c.is_ascii_digit()
 }
 
 fn is_alpha(c: char) -> bool {
-    c >= 'a' && c <= 'z' || c >= 'A' && c <= 'Z' || c == '_'
+    c.is_ascii_alphabetic() || c == '_'
 }
 
 fn is_alphanumeric(c: char) -> bool {
@@ -274,19 +513,32 @@ fn is_alphanumeric(c: char) -> bool {
 fn keyword_map() -> HashMap<String, TokenType> {
     [
         ("and".to_string(), TokenType::And),
+        ("as".to_string(), TokenType::As),
+        ("break".to_string(), TokenType::Break),
+        ("case".to_string(), TokenType::Case),
+        ("catch".to_string(), TokenType::Catch),
         ("class".to_string(), TokenType::Class),
+        ("continue".to_string(), TokenType::Continue),
+        ("default".to_string(), TokenType::Default),
+        ("do".to_string(), TokenType::Do),
         ("else".to_string(), TokenType::Else),
         ("false".to_string(), TokenType::False),
+        ("finally".to_string(), TokenType::Finally),
         ("for".to_string(), TokenType::For),
         ("fun".to_string(), TokenType::Fun),
         ("if".to_string(), TokenType::If),
+        ("import".to_string(), TokenType::Import),
+        ("in".to_string(), TokenType::In),
+        ("match".to_string(), TokenType::Match),
         ("nil".to_string(), TokenType::Nil),
         ("or".to_string(), TokenType::Or),
         ("print".to_string(), TokenType::Print),
         ("return".to_string(), TokenType::Return),
         ("super".to_string(), TokenType::Super),
         ("this".to_string(), TokenType::This),
+        ("throw".to_string(), TokenType::Throw),
         ("true".to_string(), TokenType::True),
+        ("try".to_string(), TokenType::Try),
         ("var".to_string(), TokenType::Var),
         ("while".to_string(), TokenType::While),
     ]
@@ -299,4 +551,12 @@ pub enum ScannerError {
     /// An unreconized symbol was found
     UnrecognizedSymbol(u64, char),
     UnterminatedString(u64),
+    /// Contains the line of a `${` that was never closed
+    UnterminatedInterpolation(u64),
+    /// Contains the line of a `${}` with nothing inside it
+    EmptyInterpolation(u64),
+    /// Contains the line and the text of an escape sequence that isn't supported
+    InvalidEscape(u64, String),
+    /// Contains the line and the text of a malformed number literal
+    InvalidNumber(u64, String),
 }
@@ -1,18 +1,29 @@
+use std::rc::Rc;
+
 #[derive(Debug, Clone)]
 pub struct Token {
     pub t_type: TokenType,
     pub lexeme: String,
     pub literal: TokenLiteral,
     pub line: u64,
+    /// The file the token was scanned from, `None` for code typed into the prompt
+    pub file: Option<Rc<str>>,
 }
 
 impl Token {
-    pub fn new(t_type: TokenType, lexeme: String, literal: TokenLiteral, line: u64) -> Self {
+    pub fn new(
+        t_type: TokenType,
+        lexeme: String,
+        literal: TokenLiteral,
+        line: u64,
+        file: Option<Rc<str>>,
+    ) -> Self {
         Self {
             t_type,
             lexeme,
             literal,
             line,
+            file,
         }
     }
 }
@@ -30,6 +41,7 @@ impl std::fmt::Display for Token {
 pub enum TokenLiteral {
     None,
     String(String),
+    Integer(i64),
     Number(f64),
 }
 
@@ -40,13 +52,15 @@ pub enum TokenType {
     RightParen,
     LeftBrace,
     RightBrace,
+    LeftBracket,
+    RightBracket,
     Comma,
     Dot,
-    Minus,
-    Plus,
+    DotDot,
+    Percent,
     Semicolon,
-    Slash,
-    Star,
+    Question,
+    Colon,
     // One or two character tokens.
     Bang,
     BangEqual,
@@ -56,25 +70,52 @@ pub enum TokenType {
     GreaterEqual,
     Less,
     LessEqual,
+    Minus,
+    MinusEqual,
+    MinusMinus,
+    Plus,
+    PlusEqual,
+    PlusPlus,
+    Slash,
+    SlashEqual,
+    Star,
+    StarEqual,
+    StarStar,
+    TildeSlash,
     // Literals.
     Identifier,
     String,
+    /// The part of a string before an interpolated expression
+    Interpolation,
     Number,
     // Keywords.
     And,
+    As,
+    Break,
+    Case,
+    Catch,
     Class,
+    Continue,
+    Default,
+    Do,
     Else,
     False,
+    Finally,
     Fun,
     For,
     If,
+    Import,
+    In,
+    Match,
     Nil,
     Or,
     Print,
     Return,
     Super,
     This,
+    Throw,
     True,
+    Try,
     Var,
     While,
 
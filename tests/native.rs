@@ -0,0 +1,106 @@
+//! Runs lox scripts that call natives registered from rust
+
+use std::{cell::RefCell, env, fs, path::PathBuf, process::Command, rc::Rc};
+
+use rlox::{Lox, RunTimeError, Value};
+
+/// Set in the child process that runs a script whose native error isn't caught
+const UNCAUGHT_CHILD: &str = "RLOX_UNCAUGHT_NATIVE_ERROR";
+
+/// Writes the source to a temporary lox file and returns its path
+fn script(name: &str, source: &str) -> PathBuf {
+    let path = env::temp_dir().join(format!("rlox-{}-{name}.lox", std::process::id()));
+    fs::write(&path, source).unwrap();
+    path
+}
+
+/// Runs the source as a file, exiting the process if it fails
+fn run(lox: &mut Lox, name: &str, source: &str) {
+    let path = script(name, source);
+    lox.run_file(path.to_string_lossy().into_owned());
+    fs::remove_file(path).unwrap();
+}
+
+/// An interpreter with a `record` native that collects what each call receives
+fn recording_lox() -> (Lox, Rc<RefCell<Vec<String>>>) {
+    let recorded = Rc::new(RefCell::new(Vec::new()));
+    let sink = Rc::clone(&recorded);
+    let mut lox = Lox::new();
+    lox.define_native("record", 1, move |args| {
+        sink.borrow_mut().push(args[0].to_string());
+        Ok(Value::Nil)
+    });
+    (lox, recorded)
+}
+
+fn fail(_: &[Value]) -> Result<Value, RunTimeError> {
+    Err(RunTimeError::native("out of coffee"))
+}
+
+#[test]
+fn natives_are_called_with_their_arguments() {
+    let (mut lox, recorded) = recording_lox();
+    lox.define_native("greet", 1, |args| match &args[0] {
+        Value::String(name) => Ok(Value::String(format!("Hello, {name}!"))),
+        value => Err(RunTimeError::native(format!("Can't greet {value}"))),
+    });
+
+    run(
+        &mut lox,
+        "call",
+        "record(greet(\"lox\"));\nvar alias = greet;\nrecord(alias(\"natives\"));\n",
+    );
+
+    assert_eq!(*recorded.borrow(), ["Hello, lox!", "Hello, natives!"]);
+}
+
+#[test]
+fn native_errors_are_reported_at_the_call() {
+    if env::var_os(UNCAUGHT_CHILD).is_some() {
+        let mut lox = Lox::new();
+        lox.define_native("fail", 0, fail);
+        run(
+            &mut lox,
+            "uncaught",
+            "print \"before\";\nfail();\nprint \"after\";\n",
+        );
+        return;
+    }
+
+    // The error exits the process, so the script runs in a copy of this test
+    let output = Command::new(env::current_exe().unwrap())
+        .args([
+            "native_errors_are_reported_at_the_call",
+            "--exact",
+            "--nocapture",
+        ])
+        .env(UNCAUGHT_CHILD, "1")
+        .output()
+        .unwrap();
+
+    assert_eq!(output.status.code(), Some(65));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("before") && !stdout.contains("after"),
+        "{stdout}"
+    );
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("line 2] Error at ')': out of coffee"),
+        "{stderr}"
+    );
+}
+
+#[test]
+fn native_errors_can_be_caught() {
+    let (mut lox, recorded) = recording_lox();
+    lox.define_native("fail", 0, fail);
+
+    run(
+        &mut lox,
+        "caught",
+        "try {\n  fail();\n} catch (e) {\n  record(e.message);\n  record(e.line);\n}\n",
+    );
+
+    assert_eq!(*recorded.borrow(), ["out of coffee", "2"]);
+}